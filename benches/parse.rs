@@ -0,0 +1,108 @@
+#[macro_use]
+extern crate criterion;
+extern crate asn1_der;
+
+use asn1_der::{Parser, Writer};
+use asn1_der::owned::OwnedAsn1Value;
+use criterion::{Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations, so that the owned conversions can report how many they make.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// A SEQUENCE of many small SEQUENCEs, each holding a few primitives, somewhat like a
+/// large certificate bundle.
+fn large_document() -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer.begin_sequence();
+    for i in 0..10000u32 {
+        writer.begin_sequence();
+        writer.write_raw(&[0x02, 0x02, (i >> 8) as u8 & 0x7f, i as u8]);
+        writer.write_raw(&[0x06, 0x03, 0x2a, 0x86, 0x48]);
+        writer.write_bool(i % 2 == 0);
+        writer.begin_sequence();
+        writer.write_raw(&[0x04, 0x04, 0xde, 0xad, 0xbe, 0xef]);
+        writer.write_raw(&[0x05, 0x00]);
+        writer.end_sequence().unwrap();
+        writer.end_sequence().unwrap();
+    }
+    writer.end_sequence().unwrap();
+    writer.into_bytes()
+}
+
+fn parse_all(input: &[u8]) -> usize {
+    let mut parser = Parser::new(input);
+    let mut count = 0;
+    while parser.next().is_ok() {
+        count += 1;
+    }
+    count
+}
+
+fn owned_separately(input: &[u8], out: &mut Vec<OwnedAsn1Value>) {
+    out.clear();
+    let mut parser = Parser::new(input);
+    while let Ok(value) = parser.next() {
+        out.extend(OwnedAsn1Value::from_value(&value));
+    }
+}
+
+fn owned_in_scratch(input: &[u8], buf: &mut Vec<u8>, out: &mut Vec<std::ops::Range<usize>>) {
+    buf.clear();
+    out.clear();
+    let mut parser = Parser::new(input);
+    while let Ok(value) = parser.next() {
+        out.extend(value.to_owned_in(buf).ok());
+    }
+}
+
+fn owned_conversions(c: &mut Criterion) {
+    let document = large_document();
+    let mut values = Vec::new();
+    let mut buf = Vec::new();
+    let mut ranges = Vec::new();
+    
+    // Warm up the reused buffers, then count what a further pass allocates.
+    owned_separately(&document, &mut values);
+    owned_in_scratch(&document, &mut buf, &mut ranges);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    owned_separately(&document, &mut values);
+    let separately = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    owned_in_scratch(&document, &mut buf, &mut ranges);
+    let in_scratch = ALLOCATIONS.load(Ordering::Relaxed) - before - separately;
+    println!("allocations per document: {} converting separately, {} in a scratch buffer", separately, in_scratch);
+    
+    let mut group = c.benchmark_group("owned");
+    group.throughput(Throughput::Bytes(document.len() as u64));
+    group.bench_function("separately", |b| b.iter(|| owned_separately(criterion::black_box(&document), &mut values)));
+    group.bench_function("scratch_buffer", |b| b.iter(|| owned_in_scratch(criterion::black_box(&document), &mut buf, &mut ranges)));
+    group.finish();
+}
+
+fn next_throughput(c: &mut Criterion) {
+    let document = large_document();
+    let mut group = c.benchmark_group("next");
+    group.throughput(Throughput::Bytes(document.len() as u64));
+    group.bench_function("large_document", |b| b.iter(|| parse_all(criterion::black_box(&document))));
+    group.finish();
+}
+
+criterion_group!(benches, next_throughput, owned_conversions);
+criterion_main!(benches);
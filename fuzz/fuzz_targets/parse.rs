@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use asn1_der::{Asn1Value, Parser};
+
+fuzz_target!(|data: &[u8]| {
+    let mut parser = Parser::new(data);
+    // Every value is either decoded or rejected; errors end the run, but nothing may panic.
+    while let Ok(value) = parser.next() {
+        match &value {
+            Asn1Value::ObjectIdentifier(oid) => { for _ in oid.iter() {} },
+            Asn1Value::Integer(x) => { let _ = (x.as_i64(), x.as_u64(), x.bit_length()); },
+            Asn1Value::BitString(x) => { let _ = x.get(x.len()); },
+            _ => {}
+        }
+        let mut out = Vec::new();
+        let _ = value.encode(&mut out);
+    }
+});
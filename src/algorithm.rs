@@ -0,0 +1,94 @@
+use object_identifier::ObjectIdentifier;
+
+/// Well-known algorithm OIDs, so that OID dispatch can be written as a `match`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KnownOid {
+    RsaEncryption,
+    Sha1WithRsa,
+    Sha256WithRsa,
+    Sha384WithRsa,
+    Sha512WithRsa,
+    RsassaPss,
+    EcPublicKey,
+    EcdsaWithSha256,
+    EcdsaWithSha384,
+    EcdsaWithSha512,
+    Ed25519,
+    Sha256,
+    Sha384,
+    Sha512,
+    /// Any other OID, given by its components, so that a catch-all arm still knows which
+    /// OID it was. The components are u64s, to hold those of OIDs from `new_large`.
+    Unknown(Vec<u64>),
+}
+
+// The encoded content octets of each OID. Since DER encodes an OID in exactly one way,
+// comparing these is the same as comparing the components.
+const KNOWN_OIDS: [(KnownOid, &[u8]); 14] = [
+    (KnownOid::RsaEncryption,   &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]), // 1.2.840.113549.1.1.1
+    (KnownOid::Sha1WithRsa,     &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x05]), // 1.2.840.113549.1.1.5
+    (KnownOid::Sha256WithRsa,   &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b]), // 1.2.840.113549.1.1.11
+    (KnownOid::Sha384WithRsa,   &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c]), // 1.2.840.113549.1.1.12
+    (KnownOid::Sha512WithRsa,   &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d]), // 1.2.840.113549.1.1.13
+    (KnownOid::RsassaPss,       &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a]), // 1.2.840.113549.1.1.10
+    (KnownOid::EcPublicKey,     &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]), // 1.2.840.10045.2.1
+    (KnownOid::EcdsaWithSha256, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]), // 1.2.840.10045.4.3.2
+    (KnownOid::EcdsaWithSha384, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03]), // 1.2.840.10045.4.3.3
+    (KnownOid::EcdsaWithSha512, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04]), // 1.2.840.10045.4.3.4
+    (KnownOid::Ed25519,         &[0x2b, 0x65, 0x70]), // 1.3.101.112
+    (KnownOid::Sha256,          &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]), // 2.16.840.1.101.3.4.2.1
+    (KnownOid::Sha384,          &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02]), // 2.16.840.1.101.3.4.2.2
+    (KnownOid::Sha512,          &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03]), // 2.16.840.1.101.3.4.2.3
+];
+
+impl KnownOid {
+    pub fn from_oid(oid: &ObjectIdentifier) -> KnownOid {
+        KNOWN_OIDS.iter()
+            .find(|&&(_, content)| content == oid.as_bytes())
+            .map(|(known, _)| known.clone())
+            .unwrap_or_else(|| KnownOid::Unknown(oid.iter_large().collect()))
+    }
+
+    /// The encoded content octets of this OID, or None for an `Unknown` one.
+    pub fn as_bytes(&self) -> Option<&'static [u8]> {
+        KNOWN_OIDS.iter()
+            .find(|&(known, _)| known == self)
+            .map(|&(_, content)| content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KnownOid, KNOWN_OIDS};
+    use object_identifier::ObjectIdentifier;
+
+    #[test]
+    fn from_oid() {
+        let rsa = ObjectIdentifier::new(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]).unwrap();
+        assert_eq!(KnownOid::from_oid(&rsa), KnownOid::RsaEncryption);
+        
+        for &(ref known, content) in KNOWN_OIDS.iter() {
+            let oid = ObjectIdentifier::new(content).unwrap();
+            assert_eq!(&KnownOid::from_oid(&oid), known);
+            assert_eq!(known.as_bytes(), Some(content));
+        }
+    }
+
+    #[test]
+    fn unknown_oid() {
+        // 1.3.6.1.4.1.311.21.20, a Microsoft OID.
+        let other = ObjectIdentifier::new(&[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x14]).unwrap();
+        let known = KnownOid::from_oid(&other);
+        match known {
+            KnownOid::Unknown(ref arcs) => { assert_eq!(arcs[..], [1, 3, 6, 1, 4, 1, 311, 21, 20]); },
+            _ => { panic!("Expected an unknown OID"); }
+        }
+        assert_eq!(known.as_bytes(), None);
+        
+        // OIDs differing only in an arc too large for a u32 stay distinct.
+        let large = ObjectIdentifier::new_large(&[0x69, 0x90, 0x80, 0x80, 0x80, 0x00]).unwrap();
+        let larger = ObjectIdentifier::new_large(&[0x69, 0x90, 0x80, 0x80, 0x80, 0x01]).unwrap();
+        assert_eq!(KnownOid::from_oid(&large), KnownOid::Unknown(vec![2, 25, 1 << 32]));
+        assert_eq!(KnownOid::from_oid(&larger), KnownOid::Unknown(vec![2, 25, (1 << 32) + 1]));
+    }
+}
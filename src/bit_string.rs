@@ -0,0 +1,181 @@
+use ::Parser;
+use error::Error;
+#[cfg(feature = "bitflags")]
+use std::{cmp, mem};
+#[cfg(feature = "bitflags")]
+use std::convert::TryFrom;
+
+#[derive(Debug, Copy, Clone)]
+pub struct BitString<'a> {
+    unused_bits: u8,
+    data: &'a [u8],
+}
+
+impl<'a> BitString<'a> {
+    /// Interprets the content of a BIT STRING, which is a count of unused bits in the last
+    /// byte followed by the bytes of data. DER requires those unused bits to be zero.
+    pub fn new(content: &'a [u8]) -> Result<BitString<'a>, Error> {
+        BitString::with_padding_check(content, true)
+    }
+
+    /// Like `new`, but only requires the unused bits to be zero if `strict` is set.
+    pub fn with_padding_check(content: &'a [u8], strict: bool) -> Result<BitString<'a>, Error> {
+        let (unused_bits, data) = match content.split_first() {
+            Some((unused_bits, data)) => (*unused_bits, data),
+            None => { return Err(Error::InvalidBitString); }
+        };
+        
+        if unused_bits > 7 || (data.is_empty() && unused_bits != 0) {
+            return Err(Error::InvalidBitString);
+        }
+        
+        if strict {
+            if let Some(last) = data.last() {
+                if *last & ((1u8 << unused_bits) - 1) != 0 {
+                    return Err(Error::InvalidBitString);
+                }
+            }
+        }
+        
+        Ok(BitString{
+            unused_bits: unused_bits,
+            data: data,
+        })
+    }
+
+    pub fn unused_bits(&self) -> u8 {
+        self.unused_bits
+    }
+
+    /// The bytes holding the bits, most significant bit first. The last byte may contain
+    /// unused bits at the low end.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The number of bits in the string.
+    pub fn len(&self) -> usize {
+        self.data.len()*8 - self.unused_bits as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns bit `n`, where bit 0 is the most significant bit of the first byte, as in
+    /// ASN.1 named bit lists. Bits past the end are false.
+    pub fn get(&self, n: usize) -> bool {
+        if n >= self.len() {
+            return false;
+        }
+        
+        self.data[n / 8] & (0x80 >> (n % 8)) != 0
+    }
+
+    /// Returns a parser over the data, for BIT STRINGs that wrap a DER encoding, such as the
+    /// public key in a SubjectPublicKeyInfo. Fails with `BitStringNotByteAligned` if there
+    /// are unused bits, since the wrapped encoding must be whole bytes.
+    pub fn parse_inner(&self) -> Result<Parser<'a>, Error> {
+        if self.unused_bits != 0 {
+            return Err(Error::BitStringNotByteAligned);
+        }
+        Ok(Parser::new(self.data))
+    }
+
+    /// Converts a named bit list (such as KeyUsage) to a set of flags. ASN.1 numbers bits
+    /// from the most significant bit of the first byte, so bit `n` here becomes the flag
+    /// value `1 << n`. Bits that don't fit in `F::Bits`, or aren't defined flags, are dropped.
+    #[cfg(feature = "bitflags")]
+    pub fn to_flags<F>(&self) -> F where F: bitflags::Flags, F::Bits: TryFrom<u64> {
+        let width = cmp::min(mem::size_of::<F::Bits>() * 8, 64);
+        let mask = (0..cmp::min(self.len(), width))
+            .filter(|n| self.get(*n))
+            .fold(0u64, |accum, n| accum | (1 << n));
+        F::Bits::try_from(mask).map(F::from_bits_truncate).unwrap_or_else(|_| F::empty())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitString;
+    use ::{Asn1Value, Parser};
+    use error::Error;
+
+    #[test]
+    fn inner_der() {
+        let bs = [0x03, 0x06, 0x00, 0x30, 0x03, 0x02, 0x01, 0x2a];
+        let bits = Parser::new(&bs).next_bit_string().unwrap();
+        let mut inner = bits.parse_inner().unwrap();
+        match inner.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        assert_eq!(inner.next_integer().unwrap().as_u8(), Some(42));
+        
+        let unaligned = BitString::new(&[0x01, 0x30, 0x00]).unwrap();
+        assert_eq!(unaligned.parse_inner().err(), Some(Error::BitStringNotByteAligned));
+    }
+
+    #[test]
+    fn bits() {
+        let bits = BitString::new(&[0x05, 0xa0]).unwrap();
+        assert_eq!(bits.len(), 3);
+        assert_eq!(bits.unused_bits(), 5);
+        assert_eq!(bits.data(), [0xa0]);
+        assert!(bits.get(0));
+        assert!(!bits.get(1));
+        assert!(bits.get(2));
+        assert!(!bits.get(3));
+        
+        assert!(BitString::new(&[0x00]).unwrap().is_empty());
+        assert!(BitString::new(&[]).is_err());
+        assert!(BitString::new(&[0x01]).is_err());
+        assert!(BitString::new(&[0x08, 0x00]).is_err());
+    }
+
+    #[test]
+    fn padding() {
+        let bs = [0x03, 0x02, 0x05, 0xa1];
+        
+        let mut strict = Parser::new(&bs);
+        assert_eq!(strict.next().err(), Some(Error::InvalidBitString));
+        
+        let mut lenient = Parser::new(&bs);
+        lenient.set_strict_bit_string(false);
+        match lenient.next().unwrap() {
+            Asn1Value::BitString(bits) => {
+                assert_eq!(bits.len(), 3);
+                assert!(bits.get(0) && !bits.get(1) && bits.get(2));
+            },
+            _ => { panic!("Expected a bit string"); }
+        }
+    }
+
+    #[cfg(feature = "bitflags")]
+    bitflags! {
+        #[derive(Debug, PartialEq, Eq)]
+        struct KeyUsage: u16 {
+            const DIGITAL_SIGNATURE = 1 << 0;
+            const NON_REPUDIATION = 1 << 1;
+            const KEY_ENCIPHERMENT = 1 << 2;
+            const DATA_ENCIPHERMENT = 1 << 3;
+            const KEY_AGREEMENT = 1 << 4;
+            const KEY_CERT_SIGN = 1 << 5;
+            const CRL_SIGN = 1 << 6;
+            const ENCIPHER_ONLY = 1 << 7;
+            const DECIPHER_ONLY = 1 << 8;
+        }
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    fn flags() {
+        // digitalSignature and keyEncipherment, as in a typical TLS server certificate.
+        let usage: KeyUsage = BitString::new(&[0x05, 0xa0]).unwrap().to_flags();
+        assert_eq!(usage, KeyUsage::DIGITAL_SIGNATURE | KeyUsage::KEY_ENCIPHERMENT);
+        
+        // Bit 9 isn't a defined flag, so it's dropped.
+        let usage: KeyUsage = BitString::new(&[0x06, 0x80, 0xc0]).unwrap().to_flags();
+        assert_eq!(usage, KeyUsage::DIGITAL_SIGNATURE | KeyUsage::DECIPHER_ONLY);
+    }
+}
@@ -0,0 +1,49 @@
+use ::{Asn1Value, Parser};
+use error::Error;
+use object_identifier::ObjectIdentifier;
+
+/// Reads a CMS `ContentInfo ::= SEQUENCE { contentType OBJECT IDENTIFIER, content [0]
+/// EXPLICIT ANY DEFINED BY contentType }`, returning the content type along with the whole
+/// encoding of the content, for decoding according to that type.
+pub fn read_content_info<'a>(parser: &mut Parser<'a>) -> Result<(ObjectIdentifier<'a>, &'a [u8]), Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let content_type = try!(parser.next_object_identifier());
+    try!(parser.read_explicit(0));
+    let content = try!(parser.read_any());
+    try!(parser.expect_end());
+    try!(parser.expect_end());
+    Ok((content_type, content))
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_content_info;
+    use ::Parser;
+    use error::Error;
+
+    #[test]
+    fn content_info() {
+        // signedData, with a stand-in for the SignedData itself.
+        let bs = [0x30, 0x14,
+                  0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02,
+                  0xa0, 0x07, 0x30, 0x05, 0x02, 0x01, 0x01, 0x31, 0x00];
+        let mut parser = Parser::new(&bs);
+        let (content_type, content) = read_content_info(&mut parser).unwrap();
+        assert!(content_type.equals_dotted("1.2.840.113549.1.7.2"));
+        assert_eq!(content, &bs[15..]);
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let mut parser = Parser::new(content);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(1));
+        
+        let untagged = [0x30, 0x0d,
+                        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01,
+                        0x04, 0x00];
+        assert_eq!(read_content_info(&mut Parser::new(&untagged)).err(), Some(Error::UnexpectedType));
+    }
+}
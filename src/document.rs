@@ -0,0 +1,58 @@
+use Parser;
+
+/// An owned buffer of DER bytes that can hand out parsers over itself.
+///
+/// `Parser` borrows its input rather than owning it, so that decoded values can point
+/// directly into the original bytes without copying. That means the bytes have to live
+/// somewhere else for as long as the parser and its values are in use. When the bytes
+/// come from a `Vec<u8>` (read from a file, base64-decoded, etc.), `Document` is a
+/// convenient place to keep them.
+pub struct Document {
+    bytes: Vec<u8>,
+}
+
+impl Document {
+    pub fn new(bytes: Vec<u8>) -> Document {
+        Document{
+            bytes: bytes,
+        }
+    }
+
+    pub fn parser(&self) -> Parser<'_> {
+        Parser::new(&self.bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl From<Vec<u8>> for Document {
+    fn from(bytes: Vec<u8>) -> Document {
+        Document::new(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Document;
+    use ::Asn1Value;
+
+    #[test]
+    fn parse_owned_bytes() {
+        let doc = Document::new(vec![0x01, 0x01, 0xff]);
+        let mut parser = doc.parser();
+        match parser.next().unwrap() {
+            Asn1Value::Boolean(true) => {},
+            _ => { panic!("Expected a 'true'"); }
+        }
+        
+        // Parsers are independent of one another.
+        let mut second = doc.parser();
+        assert!(second.next().is_ok());
+    }
+}
@@ -0,0 +1,461 @@
+use std::cmp::Ordering;
+use error::Error;
+use time::DateTime;
+
+#[derive(Debug, Copy, Clone)]
+struct OpenStructure {
+    tag: u8,
+    content_start: usize,
+}
+
+/// Builds up a DER encoding in memory.
+///
+/// Structures are opened with `begin_*` and closed with the matching `end_*`. Since the
+/// length of a structure is not known until it is closed, its length octets are spliced in
+/// at that point.
+pub struct Writer {
+    output: Vec<u8>,
+    structures: Vec<OpenStructure>,
+}
+
+pub fn write_length(out: &mut Vec<u8>, length: usize) {
+    if length < 0x80 {
+        out.push(length as u8);
+        return;
+    }
+    
+    let mut length_bytes = Vec::new();
+    let mut remaining = length;
+    while remaining != 0 {
+        length_bytes.push(remaining as u8);
+        remaining >>= 8;
+    }
+    
+    out.push(0x80 | (length_bytes.len() as u8));
+    out.extend(length_bytes.iter().rev());
+}
+
+/// The order DER requires of the members of a SET OF: their whole encodings, compared
+/// byte by byte, with an encoding that is a prefix of another coming first.
+pub fn der_set_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    a.cmp(b)
+}
+
+pub fn write_tlv(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+    write_length(out, content.len());
+    out.extend_from_slice(content);
+}
+
+/// The size of a whole value (a one-byte tag, its length octets and the content) with
+/// `content_len` bytes of content.
+pub fn tlv_len(content_len: usize) -> usize {
+    let mut length_len = 1;
+    if content_len >= 0x80 {
+        let mut remaining = content_len;
+        while remaining != 0 {
+            length_len += 1;
+            remaining >>= 8;
+        }
+    }
+    1 + length_len + content_len
+}
+
+/// The size of the INTEGER encoding of `value`.
+pub fn integer_len(value: i64) -> usize {
+    // The value fits in n bytes when everything above its low 8n-1 bits is sign extension.
+    let mut content_len = 1;
+    while content_len < 8 && (value >> (content_len * 8 - 1)) != 0 && (value >> (content_len * 8 - 1)) != -1 {
+        content_len += 1;
+    }
+    tlv_len(content_len)
+}
+
+/// The size of the OBJECT IDENTIFIER encoding of `arcs`, as written by
+/// `Writer::write_oid_arcs`.
+pub fn oid_len(arcs: &[u32]) -> usize {
+    fn base128_len(value: u64) -> usize {
+        let mut len = 1;
+        while (value >> (7 * len)) != 0 {
+            len += 1;
+        }
+        len
+    }
+    
+    let first = match (arcs.first(), arcs.get(1)) {
+        (Some(x), Some(y)) => *x as u64 * 40 + *y as u64,
+        _ => 0,
+    };
+    let rest = arcs.iter().skip(2).map(|arc| base128_len(*arc as u64)).sum::<usize>();
+    tlv_len(base128_len(first) + rest)
+}
+
+/// Appends `value` in base 128, most significant group first, with the high bit set on all
+/// but the last byte, as used for OID components.
+fn write_base128(out: &mut Vec<u8>, value: u32) {
+    let mut shift = 28;
+    while shift > 0 && (value >> shift) == 0 {
+        shift -= 7;
+    }
+    while shift > 0 {
+        out.push(0x80 | ((value >> shift) & 0x7f) as u8);
+        shift -= 7;
+    }
+    out.push((value & 0x7f) as u8);
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer{
+            output: Vec::new(),
+            structures: Vec::new(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Creates a writer with room for `capacity` bytes of output, such as a total worked out
+    /// with `tlv_len` and friends, so that it doesn't need to reallocate.
+    pub fn with_capacity(capacity: usize) -> Writer {
+        Writer{
+            output: Vec::with_capacity(capacity),
+            structures: Vec::new(),
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.output
+    }
+
+    fn begin_structure(&mut self, tag: u8) {
+        self.output.push(tag);
+        self.structures.push(OpenStructure{
+            tag: tag,
+            content_start: self.output.len(),
+        });
+    }
+
+    fn end_structure(&mut self, tag: u8) -> Result<(), Error> {
+        match self.structures.last() {
+            Some(innermost) if innermost.tag == tag => {},
+            _ => { return Err(Error::UnbalancedStructure); }
+        }
+        let structure = self.structures.pop().unwrap();
+        
+        let content_length = self.output.len() - structure.content_start;
+        let mut length_bytes = Vec::new();
+        write_length(&mut length_bytes, content_length);
+        
+        let start = structure.content_start;
+        self.output.splice(start..start, length_bytes);
+        Ok(())
+    }
+
+    pub fn begin_sequence(&mut self) {
+        self.begin_structure(0x30);
+    }
+
+    pub fn end_sequence(&mut self) -> Result<(), Error> {
+        self.end_structure(0x30)
+    }
+
+    pub fn begin_set(&mut self) {
+        self.begin_structure(0x31);
+    }
+
+    pub fn end_set(&mut self) -> Result<(), Error> {
+        self.end_structure(0x31)
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        write_tlv(&mut self.output, 0x01, &[if value { 0xff } else { 0x00 }]);
+    }
+
+    /// Writes a UTCTime, which can only represent whole seconds in the years 1950 to 2049.
+    /// Anything else, or an invalid date or time, gives `InvalidTime`.
+    pub fn write_utc_time(&mut self, dt: &DateTime) -> Result<(), Error> {
+        let text = try!(dt.to_utc_time());
+        write_tlv(&mut self.output, 0x17, text.as_bytes());
+        Ok(())
+    }
+
+    /// Writes a GeneralizedTime, with a fraction of a second only if there is one. An invalid
+    /// date or time gives `InvalidTime`.
+    pub fn write_generalized_time(&mut self, dt: &DateTime) -> Result<(), Error> {
+        let text = try!(dt.to_generalized_time());
+        write_tlv(&mut self.output, 0x18, text.as_bytes());
+        Ok(())
+    }
+
+    /// Writes an OBJECT IDENTIFIER with the given components. `Parser` only reads OIDs whose
+    /// first two components fit in one byte, so under arc 2 (where X.690 would allow any
+    /// second component) a second component of 40 or more gives `ObjectIdentifierTooLarge`.
+    pub fn write_oid_arcs(&mut self, arcs: &[u32]) -> Result<(), Error> {
+        if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] >= 40) {
+            return Err(Error::MalformedObjectIdentifier);
+        }
+        if arcs[1] >= 40 {
+            return Err(Error::ObjectIdentifierTooLarge);
+        }
+        
+        // The first two components share a single subidentifier.
+        let first = arcs[0]*40 + arcs[1];
+        
+        let mut content = Vec::new();
+        write_base128(&mut content, first);
+        for arc in &arcs[2..] {
+            write_base128(&mut content, *arc);
+        }
+        
+        write_tlv(&mut self.output, 0x06, &content);
+        Ok(())
+    }
+
+    /// Appends already-encoded bytes verbatim. It is up to the caller to make sure that they
+    /// form complete, valid values; nothing is checked.
+    pub fn write_raw(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    /// Writes a SET OF whose members are given already encoded. DER requires the members
+    /// to appear in ascending order of their encodings, so they are sorted first.
+    pub fn write_sorted_set(&mut self, members: &[Vec<u8>]) {
+        let mut sorted: Vec<&[u8]> = members.iter().map(|x| &x[..]).collect();
+        sorted.sort_by(|a, b| der_set_cmp(a, b));
+        write_tlv(&mut self.output, 0x31, &sorted.concat());
+    }
+}
+
+impl Default for Writer {
+    fn default() -> Writer {
+        Writer::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Writer, der_set_cmp, integer_len, oid_len, tlv_len, write_length, write_tlv};
+    use std::cmp::Ordering;
+    use ::{Asn1Value, Parser};
+    use error::Error;
+    use time::DateTime;
+
+    #[test]
+    fn lengths() {
+        fn encoded(length: usize) -> Vec<u8> {
+            let mut out = Vec::new();
+            write_length(&mut out, length);
+            out
+        }
+        
+        assert_eq!(encoded(0), [0x00]);
+        assert_eq!(encoded(127), [0x7f]);
+        assert_eq!(encoded(128), [0x81, 0x80]);
+        assert_eq!(encoded(256), [0x82, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn encoded_lengths() {
+        for &content_len in [0, 1, 127, 128, 255, 256, 65535, 65536].iter() {
+            let mut out = Vec::new();
+            write_tlv(&mut out, 0x04, &vec![0; content_len]);
+            assert_eq!(tlv_len(content_len), out.len());
+        }
+        assert_eq!(tlv_len(127), 129);
+        assert_eq!(tlv_len(128), 131);
+        
+        for &value in [0, 1, 127, 128, -1, -128, -129, 32767, 32768, i64::MAX, i64::MIN].iter() {
+            let bytes = value.to_be_bytes();
+            let mut content = &bytes[..];
+            while content.len() > 1 && ((content[0] == 0x00 && content[1] & 0x80 == 0)
+                                     || (content[0] == 0xff && content[1] & 0x80 != 0)) {
+                content = &content[1..];
+            }
+            let mut out = Vec::new();
+            write_tlv(&mut out, 0x02, content);
+            assert_eq!(integer_len(value), out.len(), "{}", value);
+        }
+        
+        let oids: &[&[u32]] = &[&[1, 2], &[1, 2, 840, 113549, 1, 1, 11], &[2, 39, 0], &[2, 25, u32::MAX]];
+        for arcs in oids.iter() {
+            let mut writer = Writer::with_capacity(oid_len(arcs));
+            writer.write_oid_arcs(arcs).unwrap();
+            assert_eq!(oid_len(arcs), writer.as_bytes().len());
+            
+            let parsed: Vec<u32> = Parser::new(writer.as_bytes()).next_object_identifier().unwrap().iter().collect();
+            assert_eq!(&parsed[..], *arcs);
+        }
+    }
+
+    #[test]
+    fn times() {
+        let dt = DateTime{ year: 2024, month: 3, day: 9, hour: 7, minute: 5, second: 0, nanosecond: 0 };
+        let mut writer = Writer::new();
+        writer.begin_sequence();
+        writer.write_utc_time(&dt).unwrap();
+        writer.write_generalized_time(&dt).unwrap();
+        writer.end_sequence().unwrap();
+        let bytes = writer.into_bytes();
+        assert_eq!(&bytes[2..17], b"\x17\x0d240309070500Z");
+        assert_eq!(&bytes[17..], b"\x18\x0f20240309070500Z");
+        
+        let mut parser = Parser::new(&bytes);
+        assert!(parser.next().is_ok());
+        match parser.next().unwrap() {
+            Asn1Value::UtcTime(x) => { assert_eq!(x, dt); },
+            _ => { panic!("Expected a UTCTime"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::GeneralizedTime(x) => { assert_eq!(x, dt); },
+            _ => { panic!("Expected a GeneralizedTime"); }
+        }
+        
+        let mut writer = Writer::new();
+        let bad_month = DateTime{ month: 13, ..dt };
+        assert_eq!(writer.write_utc_time(&bad_month), Err(Error::InvalidTime));
+        assert_eq!(writer.write_generalized_time(&bad_month), Err(Error::InvalidTime));
+        let too_late = DateTime{ year: 2050, ..dt };
+        assert_eq!(writer.write_utc_time(&too_late), Err(Error::InvalidTime));
+        assert!(writer.as_bytes().is_empty());
+    }
+
+    #[test]
+    fn set_ordering() {
+        assert_eq!(der_set_cmp(&[0x02, 0x01, 0x01], &[0x02, 0x01, 0x05]), Ordering::Less);
+        assert_eq!(der_set_cmp(&[0x04, 0x00], &[0x02, 0x01, 0x05]), Ordering::Greater);
+        assert_eq!(der_set_cmp(&[0x02, 0x01, 0x05], &[0x02, 0x01, 0x05]), Ordering::Equal);
+        
+        // A prefix sorts first, even when the longer one continues with a zero byte.
+        assert_eq!(der_set_cmp(&[0x04, 0x01], &[0x04, 0x01, 0x00]), Ordering::Less);
+        assert_eq!(der_set_cmp(&[0x04, 0x01, 0x00], &[0x04, 0x01]), Ordering::Greater);
+        assert_eq!(der_set_cmp(&[], &[0x00]), Ordering::Less);
+        assert_eq!(der_set_cmp(&[], &[]), Ordering::Equal);
+    }
+
+    #[test]
+    fn sorted_set() {
+        let members = vec![
+            vec![0x02, 0x01, 0x05],
+            vec![0x02, 0x02, 0x01, 0x00],
+            vec![0x02, 0x01, 0x01],
+            vec![0x01, 0x01, 0xff],
+        ];
+        
+        let mut writer = Writer::new();
+        writer.write_sorted_set(&members);
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, [0x31, 0x0d,
+                           0x01, 0x01, 0xff,
+                           0x02, 0x01, 0x01,
+                           0x02, 0x01, 0x05,
+                           0x02, 0x02, 0x01, 0x00]);
+        
+        let mut parser = Parser::new(&bytes);
+        parser.expect_set_of().unwrap();
+        
+        // The set doesn't disturb a structure that is still open.
+        let mut writer = Writer::new();
+        writer.begin_sequence();
+        writer.write_sorted_set(&members);
+        writer.end_sequence().unwrap();
+        assert_eq!(writer.as_bytes()[..2], [0x30, 0x0f]);
+        assert_eq!(writer.as_bytes()[2..], bytes[..]);
+    }
+
+    #[test]
+    fn raw() {
+        let cached_integer = [0x02, 0x02, 0x01, 0x00];
+        
+        let mut writer = Writer::new();
+        writer.begin_sequence();
+        writer.write_raw(&cached_integer);
+        writer.end_sequence().unwrap();
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, [0x30, 0x04, 0x02, 0x02, 0x01, 0x00]);
+        
+        let mut parser = Parser::new(&bytes);
+        match parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::Integer(x) => { assert_eq!(x.as_u32(), Some(256)); },
+            _ => { panic!("Expected an integer"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+    }
+
+    #[test]
+    fn booleans() {
+        let mut writer = Writer::new();
+        writer.begin_sequence();
+        writer.write_bool(true);
+        writer.write_bool(false);
+        writer.write_bool(true);
+        writer.end_sequence().unwrap();
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, [0x30, 0x09,
+                           0x01, 0x01, 0xff,
+                           0x01, 0x01, 0x00,
+                           0x01, 0x01, 0xff]);
+        
+        let mut parser = Parser::new(&bytes);
+        match parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        assert_eq!(parser.next_boolean(), Ok(true));
+        assert_eq!(parser.next_boolean(), Ok(false));
+        assert_eq!(parser.next_boolean(), Ok(true));
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+    }
+
+    #[test]
+    fn oid_arcs() {
+        let arcs = [1, 2, 840, 113549, 1, 1, 11];
+        let mut writer = Writer::new();
+        writer.write_oid_arcs(&arcs).unwrap();
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, [0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b]);
+        
+        let mut parser = Parser::new(&bytes);
+        let parsed: Vec<u32> = parser.next_object_identifier().unwrap().iter().collect();
+        assert_eq!(parsed, arcs);
+        
+        let arcs = [2, 39, 0xffffffff];
+        let mut writer = Writer::new();
+        writer.write_oid_arcs(&arcs).unwrap();
+        assert_eq!(writer.as_bytes(), [0x06, 0x06, 0x77, 0x8f, 0xff, 0xff, 0xff, 0x7f]);
+        let parsed: Vec<u32> = Parser::new(writer.as_bytes()).next_object_identifier().unwrap().iter().collect();
+        assert_eq!(parsed, arcs);
+        
+        assert_eq!(writer.write_oid_arcs(&[1]), Err(Error::MalformedObjectIdentifier));
+        assert_eq!(writer.write_oid_arcs(&[3, 1]), Err(Error::MalformedObjectIdentifier));
+        assert_eq!(writer.write_oid_arcs(&[1, 40]), Err(Error::MalformedObjectIdentifier));
+        // These would take more than one byte for the first two components.
+        assert_eq!(writer.write_oid_arcs(&[2, 40]), Err(Error::ObjectIdentifierTooLarge));
+        assert_eq!(writer.write_oid_arcs(&[2, 999, 3]), Err(Error::ObjectIdentifierTooLarge));
+        assert_eq!(writer.write_oid_arcs(&[2, 0xffffffff]), Err(Error::ObjectIdentifierTooLarge));
+        assert_eq!(writer.as_bytes().len(), 8);
+    }
+
+    #[test]
+    fn unbalanced() {
+        let mut writer = Writer::new();
+        writer.begin_sequence();
+        match writer.end_set() {
+            Err(Error::UnbalancedStructure) => {},
+            _ => { panic!("Expected an unbalanced structure error"); }
+        }
+        writer.end_sequence().unwrap();
+        assert_eq!(writer.as_bytes(), [0x30, 0x00]);
+    }
+}
@@ -0,0 +1,386 @@
+use std::cmp::Ordering;
+use error::Error;
+use encoder::{der_set_cmp, write_length};
+use tag::{Tag, TagClass};
+use {decode_ber_length, MAX_NESTING_DEPTH};
+
+/// Whether a document is valid DER, or only BER.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Der,
+    /// The earliest BER-only feature in the document, and the offset of the value using it.
+    Ber { offset: usize, reason: BerFeature },
+}
+
+/// Encodings that BER allows but DER doesn't.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BerFeature {
+    IndefiniteLength,
+    NonMinimalLength,
+    NonCanonicalBoolean,
+    NonZeroBitStringPadding,
+    ConstructedString,
+    UnsortedSet,
+}
+
+/// Checks the document for BER-only features, without stopping at the first one. Returns
+/// an error only if the document isn't even valid BER (as far as these checks can tell), or
+/// if structures nest more than `MAX_NESTING_DEPTH` deep.
+pub fn encoding_kind(input: &[u8]) -> Result<Encoding, Error> {
+    let mut found = None;
+    try!(scan_values(input, 0, false, 0, &mut Vec::new(), &mut found));
+    Ok(match found {
+        Some((offset, reason)) => Encoding::Ber{ offset: offset, reason: reason },
+        None => Encoding::Der,
+    })
+}
+
+fn note(found: &mut Option<(usize, BerFeature)>, offset: usize, reason: BerFeature) {
+    match *found {
+        Some((earliest, _)) if earliest <= offset => {},
+        _ => { *found = Some((offset, reason)); }
+    }
+}
+
+fn is_string_type(number: u32) -> bool {
+    matches!(number, 0x03 | 0x04 | 0x0C | 0x12..=0x1E)
+}
+
+/// Scans the values in `bytes`, which starts `base` bytes into the document, recording the
+/// span of each one in `spans`. If `until_end_of_contents`, the values are ended by a pair
+/// of zero bytes, as in an indefinite-length value. `depth` is how many structures enclose
+/// `bytes`. Returns the number of bytes used.
+fn scan_values(bytes: &[u8], base: usize, until_end_of_contents: bool, depth: usize,
+               spans: &mut Vec<(usize, usize)>, found: &mut Option<(usize, BerFeature)>) -> Result<usize, Error> {
+    let mut position = 0;
+    loop {
+        if position == bytes.len() {
+            if until_end_of_contents {
+                return Err(Error::EOF);
+            }
+            return Ok(position);
+        }
+        if until_end_of_contents && bytes[position..].starts_with(&[0, 0]) {
+            return Ok(position + 2);
+        }
+        
+        let offset = base + position;
+        let (tag, tag_size) = try!(Tag::decode(&bytes[position..]));
+        let (length, length_size, minimal) = try!(decode_ber_length(&bytes[position + tag_size..]));
+        let content_start = position + tag_size + length_size;
+        if !minimal {
+            note(found, offset, BerFeature::NonMinimalLength);
+        }
+        
+        let universal = tag.class == TagClass::Universal;
+        if universal && tag.constructed && is_string_type(tag.number) {
+            note(found, offset, BerFeature::ConstructedString);
+        }
+        if tag.constructed && depth == MAX_NESTING_DEPTH {
+            return Err(Error::NestingTooDeep);
+        }
+        
+        let length = match length {
+            Some(length) => length,
+            None => {
+                if !tag.constructed {
+                    return Err(Error::Malformed);
+                }
+                note(found, offset, BerFeature::IndefiniteLength);
+                let used = try!(scan_values(&bytes[content_start..], base + content_start, true, depth + 1, &mut Vec::new(), found));
+                position = content_start + used;
+                spans.push((offset, base + position));
+                continue;
+            }
+        };
+        
+        if length > bytes.len() - content_start {
+            return Err(Error::EOF);
+        }
+        let content = &bytes[content_start .. content_start + length];
+        
+        if tag.constructed {
+            let mut children = Vec::new();
+            try!(scan_values(content, base + content_start, false, depth + 1, &mut children, found));
+            
+            if universal && tag.number == 0x11 {
+                let sorted = children.windows(2).all(|pair| {
+                    let (a, b) = (pair[0], pair[1]);
+                    der_set_cmp(&bytes[a.0 - base .. a.1 - base], &bytes[b.0 - base .. b.1 - base]) != Ordering::Greater
+                });
+                if !sorted {
+                    note(found, offset, BerFeature::UnsortedSet);
+                }
+            }
+        } else if universal && tag.number == 0x01 {
+            if content != [0x00] && content != [0xff] {
+                note(found, offset, BerFeature::NonCanonicalBoolean);
+            }
+        } else if universal && tag.number == 0x03 {
+            if let Some((&unused_bits, data)) = content.split_first() {
+                let padding_mask = (1u16 << unused_bits.min(8)) - 1;
+                if data.last().is_some_and(|last| (*last as u16) & padding_mask != 0) {
+                    note(found, offset, BerFeature::NonZeroBitStringPadding);
+                }
+            }
+        }
+        
+        position = content_start + length;
+        spans.push((offset, base + position));
+    }
+}
+
+/// A value converted to DER, held as its tag and encoded content.
+struct DerValue {
+    tag: Tag,
+    content: Vec<u8>,
+}
+
+impl DerValue {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tag.encode(out);
+        write_length(out, self.content.len());
+        out.extend_from_slice(&self.content);
+    }
+}
+
+/// Re-encodes BER input as DER: lengths become definite and minimal, constructed strings
+/// are joined into primitive ones, and BOOLEANs, INTEGERs, BIT STRINGs and SETs are put in
+/// their canonical forms. Structures nested more than `MAX_NESTING_DEPTH` deep give
+/// `NestingTooDeep`.
+pub fn ber_to_der(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let (values, _) = try!(convert_values(input, false, 0));
+    let mut out = Vec::with_capacity(input.len());
+    for value in values.iter() {
+        value.encode(&mut out);
+    }
+    Ok(out)
+}
+
+/// Converts the values in `bytes` to DER, returning them along with the number of bytes
+/// used. If `until_end_of_contents`, the values are ended by a pair of zero bytes. `depth` is
+/// how many structures enclose `bytes`.
+fn convert_values(bytes: &[u8], until_end_of_contents: bool, depth: usize) -> Result<(Vec<DerValue>, usize), Error> {
+    let mut values = Vec::new();
+    let mut position = 0;
+    loop {
+        if position == bytes.len() {
+            if until_end_of_contents {
+                return Err(Error::EOF);
+            }
+            return Ok((values, position));
+        }
+        if until_end_of_contents && bytes[position..].starts_with(&[0, 0]) {
+            return Ok((values, position + 2));
+        }
+        
+        let (tag, tag_size) = try!(Tag::decode(&bytes[position..]));
+        let (length, length_size, _) = try!(decode_ber_length(&bytes[position + tag_size..]));
+        let content_start = position + tag_size + length_size;
+        
+        let value = if tag.constructed {
+            if depth == MAX_NESTING_DEPTH {
+                return Err(Error::NestingTooDeep);
+            }
+            let children = match length {
+                Some(length) => {
+                    if length > bytes.len() - content_start {
+                        return Err(Error::EOF);
+                    }
+                    position = content_start + length;
+                    try!(convert_values(&bytes[content_start..position], false, depth + 1)).0
+                }
+                None => {
+                    let (children, used) = try!(convert_values(&bytes[content_start..], true, depth + 1));
+                    position = content_start + used;
+                    children
+                }
+            };
+            try!(convert_constructed(tag, children))
+        } else {
+            let length = try!(length.ok_or(Error::Malformed));
+            if length > bytes.len() - content_start {
+                return Err(Error::EOF);
+            }
+            position = content_start + length;
+            try!(convert_primitive(tag, &bytes[content_start..position]))
+        };
+        values.push(value);
+    }
+}
+
+fn convert_constructed(tag: Tag, children: Vec<DerValue>) -> Result<DerValue, Error> {
+    let universal = tag.class == TagClass::Universal;
+    
+    if universal && is_string_type(tag.number) {
+        // Join the segments. A BIT STRING's segments each start with their unused bit count,
+        // which must be zero for all but the last.
+        let primitive = Tag{ constructed: false, ..tag };
+        let mut content = Vec::new();
+        let mut unused_bits = 0;
+        for segment in children.iter() {
+            let expected_number = if tag.number == 0x03 { 0x03 } else { 0x04 };
+            if segment.tag.class != TagClass::Universal || segment.tag.number != expected_number || unused_bits != 0 {
+                return Err(Error::Malformed);
+            }
+            if tag.number == 0x03 {
+                unused_bits = segment.content[0];
+                content.extend_from_slice(&segment.content[1..]);
+            } else {
+                content.extend_from_slice(&segment.content);
+            }
+        }
+        
+        if tag.number == 0x03 {
+            content.insert(0, unused_bits);
+            return convert_primitive(primitive, &content);
+        }
+        return Ok(DerValue{ tag: primitive, content: content });
+    }
+    
+    let mut encodings: Vec<Vec<u8>> = children.iter().map(|child| {
+        let mut encoding = Vec::new();
+        child.encode(&mut encoding);
+        encoding
+    }).collect();
+    if universal && tag.number == 0x11 {
+        encodings.sort_by(|a, b| der_set_cmp(a, b));
+    }
+    Ok(DerValue{ tag: tag, content: encodings.concat() })
+}
+
+fn convert_primitive(tag: Tag, content: &[u8]) -> Result<DerValue, Error> {
+    let mut content = content.to_vec();
+    if tag.class == TagClass::Universal {
+        match tag.number {
+            0x01 => {
+                if content.len() != 1 {
+                    return Err(Error::IncorrectLength);
+                }
+                if content[0] != 0 {
+                    content[0] = 0xff;
+                }
+            }
+            0x02 | 0x0A => {
+                if content.is_empty() {
+                    return Err(Error::Malformed);
+                }
+                let redundant = content.windows(2).take_while(|pair| {
+                    (pair[0] == 0x00 && pair[1] & 0x80 == 0) || (pair[0] == 0xff && pair[1] & 0x80 != 0)
+                }).count();
+                content.drain(..redundant);
+            }
+            0x03 => {
+                let unused_bits = match content.first() {
+                    Some(unused_bits) if *unused_bits < 8 => *unused_bits,
+                    _ => { return Err(Error::InvalidBitString); }
+                };
+                if content.len() == 1 && unused_bits != 0 {
+                    return Err(Error::InvalidBitString);
+                }
+                if let Some(last) = content.last_mut() {
+                    *last &= 0xffu8 << unused_bits;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(DerValue{ tag: tag, content: content })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ber_to_der, encoding_kind, BerFeature, Encoding};
+    use error::Error;
+    use encoder::Writer;
+    use MAX_NESTING_DEPTH;
+
+    /// `depth` SEQUENCEs, each inside the last, with indefinite lengths.
+    fn nested_indefinite(depth: usize) -> Vec<u8> {
+        let mut bs = [0x30, 0x80].repeat(depth);
+        bs.extend(vec![0x00; 2*depth]);
+        bs
+    }
+
+    fn ber(offset: usize, reason: BerFeature) -> Result<Encoding, Error> {
+        Ok(Encoding::Ber{ offset: offset, reason: reason })
+    }
+
+    #[test]
+    fn der() {
+        let bs = [0x30, 0x0c,
+                  0x01, 0x01, 0xff,
+                  0x03, 0x02, 0x04, 0x10,
+                  0x31, 0x03, 0x02, 0x01, 0x01];
+        assert_eq!(encoding_kind(&bs), Ok(Encoding::Der));
+        assert_eq!(encoding_kind(&[]), Ok(Encoding::Der));
+    }
+
+    #[test]
+    fn ber_features() {
+        assert_eq!(encoding_kind(&[0x05, 0x00, 0x30, 0x80, 0x05, 0x00, 0x00, 0x00]), ber(2, BerFeature::IndefiniteLength));
+        assert_eq!(encoding_kind(&[0x04, 0x81, 0x01, 0xaa]), ber(0, BerFeature::NonMinimalLength));
+        assert_eq!(encoding_kind(&[0x04, 0x82, 0x00, 0x01, 0xaa]), ber(0, BerFeature::NonMinimalLength));
+        assert_eq!(encoding_kind(&[0x01, 0x01, 0x01]), ber(0, BerFeature::NonCanonicalBoolean));
+        assert_eq!(encoding_kind(&[0x03, 0x02, 0x04, 0x11]), ber(0, BerFeature::NonZeroBitStringPadding));
+        assert_eq!(encoding_kind(&[0x24, 0x03, 0x04, 0x01, 0xaa]), ber(0, BerFeature::ConstructedString));
+        assert_eq!(encoding_kind(&[0x31, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01]), ber(0, BerFeature::UnsortedSet));
+        
+        // The unsorted SET starts before the boolean inside it.
+        let bs = [0x31, 0x06, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00];
+        assert_eq!(encoding_kind(&bs), ber(0, BerFeature::UnsortedSet));
+    }
+
+    #[test]
+    fn not_ber() {
+        assert_eq!(encoding_kind(&[0x30, 0x80, 0x05, 0x00]), Err(Error::EOF));
+        assert_eq!(encoding_kind(&[0x04, 0x80, 0x00, 0x00]), Err(Error::Malformed));
+        assert_eq!(encoding_kind(&[0x04, 0x02, 0xaa]), Err(Error::EOF));
+        assert_eq!(encoding_kind(&[0x04, 0xff]), Err(Error::ReservedLength));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        assert_eq!(encoding_kind(&nested_indefinite(MAX_NESTING_DEPTH)), ber(0, BerFeature::IndefiniteLength));
+        assert_eq!(encoding_kind(&nested_indefinite(MAX_NESTING_DEPTH + 1)), Err(Error::NestingTooDeep));
+        assert_eq!(encoding_kind(&[0x30, 0x80].repeat(100_000)), Err(Error::NestingTooDeep));
+        
+        let mut der = Writer::new();
+        for _ in 0..MAX_NESTING_DEPTH {
+            der.begin_sequence();
+        }
+        for _ in 0..MAX_NESTING_DEPTH {
+            der.end_sequence().unwrap();
+        }
+        assert_eq!(ber_to_der(&nested_indefinite(MAX_NESTING_DEPTH)), Ok(der.into_bytes()));
+        assert_eq!(ber_to_der(&nested_indefinite(MAX_NESTING_DEPTH + 1)), Err(Error::NestingTooDeep));
+        assert_eq!(ber_to_der(&[0x30, 0x80].repeat(100_000)), Err(Error::NestingTooDeep));
+    }
+
+    #[test]
+    fn conversion() {
+        let indefinite = [0x30, 0x80, 0x02, 0x01, 0x05, 0x30, 0x80, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(ber_to_der(&indefinite), Ok(vec![0x30, 0x07, 0x02, 0x01, 0x05, 0x30, 0x02, 0x05, 0x00]));
+        
+        let cases: [(&[u8], &[u8]); 7] = [
+            (&[0x04, 0x81, 0x01, 0xaa], &[0x04, 0x01, 0xaa]),
+            (&[0x01, 0x01, 0x01], &[0x01, 0x01, 0xff]),
+            (&[0x02, 0x03, 0x00, 0x00, 0x7f], &[0x02, 0x01, 0x7f]),
+            (&[0x02, 0x02, 0xff, 0x80], &[0x02, 0x01, 0x80]),
+            (&[0x03, 0x02, 0x04, 0x1f], &[0x03, 0x02, 0x04, 0x10]),
+            (&[0x24, 0x80, 0x04, 0x01, 0xaa, 0x24, 0x03, 0x04, 0x01, 0xbb, 0x00, 0x00], &[0x04, 0x02, 0xaa, 0xbb]),
+            (&[0x31, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01], &[0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02]),
+        ];
+        for &(ber, der) in cases.iter() {
+            let converted = ber_to_der(ber).unwrap();
+            assert_eq!(&converted[..], der);
+            assert_eq!(encoding_kind(&converted), Ok(Encoding::Der));
+        }
+        
+        let split_bit_string = [0x23, 0x09, 0x03, 0x02, 0x00, 0xaa, 0x03, 0x03, 0x04, 0xbb, 0xcf];
+        assert_eq!(ber_to_der(&split_bit_string), Ok(vec![0x03, 0x04, 0x04, 0xaa, 0xbb, 0xc0]));
+        
+        assert_eq!(ber_to_der(&[0x30, 0x80, 0x05, 0x00]), Err(Error::EOF));
+        assert_eq!(ber_to_der(&[0x24, 0x03, 0x02, 0x01, 0x00]), Err(Error::Malformed));
+    }
+}
@@ -1,8 +1,9 @@
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Error {
     EOF,
     OverlongLength,
+    ReservedLength,
     InvalidLengthEncoding,
     UnrecognizedType,
     NotImplemented,
@@ -13,4 +14,59 @@ pub enum Error {
     InvalidUTF8,
     InvalidPrintableString,
     StructureOverrun,
+    UnexpectedType,
+    SetNotSorted,
+    UnbalancedStructure,
+    TagTooLarge,
+    UnencodableValue,
+    InvalidBitString,
+    EndOfStructure,
+    ConstructedMismatch,
+    InvalidIA5String,
+    InvalidBMPString,
+    InvalidUniversalString,
+    InvalidReal,
+    InvalidTime,
+    IntegerTooLong,
+    DefaultValueEncoded,
+    UnknownEnumerant,
+    DisallowedControlCharacter,
+    WrongElementCount,
+    NegativeModulus,
+    TrailingData,
+    TooManyValues,
+    BitStringNotByteAligned,
+    MissingAlgorithmParameters,
+    InvalidPem,
+    AllocationBudgetExceeded,
+    NestingTooDeep,
+    /// A structure ends partway through the tag or length of a value inside it, so the bytes
+    /// left over at its end can't be a value at all.
+    IncompleteStructureContent,
+    /// The INTEGER doesn't fit in the `target` type. `negative` distinguishes a negative
+    /// value (which may just be the wrong signedness) from one that's too large.
+    IntegerOutOfRange { target: &'static str, negative: bool },
+}
+
+impl Error {
+    /// Whether parsing can carry on after this error by skipping the offending value. This
+    /// is the case when the value's tag and length were read successfully.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(*self,
+            Error::UnrecognizedType |
+            Error::NotImplemented |
+            Error::IncorrectLength |
+            Error::Malformed |
+            Error::MalformedObjectIdentifier |
+            Error::ObjectIdentifierTooLarge |
+            Error::InvalidUTF8 |
+            Error::InvalidPrintableString |
+            Error::InvalidBitString |
+            Error::InvalidIA5String |
+            Error::InvalidBMPString |
+            Error::InvalidUniversalString |
+            Error::InvalidReal |
+            Error::InvalidTime |
+            Error::DisallowedControlCharacter)
+    }
 }
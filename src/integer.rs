@@ -1,4 +1,10 @@
+use error::Error;
+use std::convert::TryFrom;
+use std::time::Duration;
+#[cfg(feature = "bigint")]
+use num_bigint::BigUint;
 
+#[derive(Copy, Clone)]
 pub struct Integer<'a>(&'a [u8]);
 
 impl<'a> Integer<'a> {
@@ -6,39 +12,220 @@ impl<'a> Integer<'a> {
         Integer(bytes)
     }
 
+    /// Returns the magnitude bytes of a non-negative integer, with any leading 0x00 padding
+    /// stripped off. Returns None if the integer is negative (or has no content at all).
+    fn unsigned_bytes(&self) -> Option<&'a [u8]> {
+        match self.0.first() {
+            Some(first) if *first & 0x80 != 0 => { return None; }
+            Some(_) => {}
+            None => { return None; }
+        }
+        
+        let leading_zeros = self.0.iter().take_while(|x| **x == 0).count();
+        Some(&self.0[leading_zeros..])
+    }
+
+    /// Returns the two's-complement bytes of the value with any redundant leading sign
+    /// bytes stripped off. Returns None if there is no content at all.
+    fn signed_bytes(&self) -> Option<&'a [u8]> {
+        if self.0.is_empty() {
+            return None;
+        }
+        
+        let mut bytes = self.0;
+        while bytes.len() > 1 {
+            let redundant = (bytes[0] == 0x00 && bytes[1] & 0x80 == 0)
+                         || (bytes[0] == 0xff && bytes[1] & 0x80 != 0);
+            if !redundant {
+                break;
+            }
+            bytes = &bytes[1..];
+        }
+        Some(bytes)
+    }
+
     pub fn as_u8(&self) -> Option<u8> {
-        if self.0.len() > 1 {
+        let bytes = self.unsigned_bytes()?;
+        if bytes.len() > 1 {
             return None;
         }
         
-        self.0.get(0).map(|x| *x)
+        Some( bytes.first().copied().unwrap_or(0) )
     }
     
     pub fn as_u32(&self) -> Option<u32> {
-        if self.0.len() > 4 {
+        let bytes = self.unsigned_bytes()?;
+        if bytes.len() > 4 {
             return None;
         }
         
-        Some( self.0.iter().fold(0u32, |accum, b| (accum<<8) | (*b as u32)) )
+        Some( bytes.iter().fold(0u32, |accum, b| (accum<<8) | (*b as u32)) )
     }
     
     pub fn as_u64(&self) -> Option<u64> {
-        if self.0.len() > 8 {
+        let bytes = self.unsigned_bytes()?;
+        if bytes.len() > 8 {
             return None;
         }
         
-        Some( self.0.iter().fold(0u64, |accum, b| (accum<<8) | (*b as u64)) )
+        Some( bytes.iter().fold(0u64, |accum, b| (accum<<8) | (*b as u64)) )
+    }
+    
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().and_then(|x| if x as i32 as i64 == x { Some(x as i32) } else { None })
+    }
+    
+    pub fn as_i64(&self) -> Option<i64> {
+        let bytes = self.signed_bytes()?;
+        if bytes.len() > 8 {
+            return None;
+        }
+        
+        let initial = if bytes[0] & 0x80 != 0 { -1i64 } else { 0i64 };
+        Some( bytes.iter().fold(initial, |accum, b| (accum<<8) | (*b as i64)) )
+    }
+    
+    /// Whether the value is zero. This doesn't depend on the encoding being minimal, so
+    /// `00 00` counts as zero too.
+    pub fn is_zero(&self) -> bool {
+        !self.0.is_empty() && self.0.iter().all(|x| *x == 0)
+    }
+    
+    /// Whether the value is encoded in as few bytes as possible, as DER requires: a leading
+    /// 0x00 or 0xff must be needed for the sign. Empty content isn't a valid encoding at all.
+    pub fn is_minimally_encoded(&self) -> bool {
+        self.signed_bytes().map(|bytes| bytes.len()) == Some(self.0.len())
+    }
+    
+    /// The number of significant bits in the magnitude of the value, so a 2048-bit RSA
+    /// modulus (encoded with a leading 0x00 sign byte) gives 2048. Zero gives 0.
+    pub fn bit_length(&self) -> usize {
+        let negative = self.0.first().map(|x| *x & 0x80 != 0).unwrap_or(false);
+        
+        if !negative {
+            return bit_length_of(self.0.iter().copied());
+        }
+        
+        // The magnitude of a negative two's-complement number is its inverse, plus one. Adding
+        // one only needs another bit when the inverse is all ones, as in -128 = 0x80.
+        let inverted_length = bit_length_of(self.0.iter().map(|x| !*x));
+        let inverted_is_all_ones = self.0.iter().rev()
+            .map(|x| !*x)
+            .enumerate()
+            .all(|(idx, x)| {
+                let bits_left = inverted_length.saturating_sub(idx*8);
+                let expected = if bits_left >= 8 { 0xff } else { (1u16 << bits_left) as u8 - 1 };
+                x == expected
+            });
+        
+        if inverted_is_all_ones {
+            inverted_length + 1
+        } else {
+            inverted_length
+        }
+    }
+    
+    /// Bit `n` of the value's two's-complement representation, counting from the least
+    /// significant bit as 0. Bits beyond the encoded bytes copy the sign bit, so they are
+    /// set for negative values.
+    pub fn test_bit(&self, n: usize) -> bool {
+        let byte_index = n / 8;
+        if byte_index >= self.0.len() {
+            return self.is_negative();
+        }
+        
+        let byte = self.0[self.0.len() - 1 - byte_index];
+        byte & (1 << (n % 8)) != 0
+    }
+    
+    /// Reads the value as a Unicode scalar value. Negative values, surrogates and anything
+    /// above 0x10FFFF give None.
+    pub fn as_char(&self) -> Option<char> {
+        self.as_u32().and_then(char::from_u32)
+    }
+    
+    /// Reads the value as a number of seconds. Returns None if it's negative or too large
+    /// for a u64.
+    pub fn as_duration(&self) -> Option<Duration> {
+        self.as_u64().map(Duration::from_secs)
+    }
+    
+    /// Approximates the value as an f64. This is lossy for large values, and saturates to
+    /// infinity for ones that are too large for an f64 at all.
+    pub fn as_f64(&self) -> f64 {
+        let negative = self.0.first().map(|x| *x & 0x80 != 0).unwrap_or(false);
+        
+        if !negative {
+            return self.0.iter().fold(0f64, |accum, b| accum*256.0 + (*b as f64));
+        }
+        
+        // The magnitude of a negative two's-complement number is its inverse, plus one.
+        let inverted = self.0.iter().fold(0f64, |accum, b| accum*256.0 + (!*b as f64));
+        -(inverted + 1.0)
     }
     
     pub fn as_bytes(&self) -> &'a [u8] {
         self.0
     }
+
+    /// Converts an RSA modulus (or any other non-negative INTEGER) to a BigUint.
+    #[cfg(feature = "bigint")]
+    pub fn as_rsa_modulus(&self) -> Result<BigUint, Error> {
+        if self.is_negative() {
+            return Err(Error::NegativeModulus);
+        }
+        self.unsigned_bytes().map(BigUint::from_bytes_be).ok_or(Error::Malformed)
+    }
+
+    /// Whether the value is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.0.first().map(|x| *x & 0x80 != 0).unwrap_or(false)
+    }
+
+    fn out_of_range(&self, target: &'static str) -> Error {
+        Error::IntegerOutOfRange{ target: target, negative: self.is_negative() }
+    }
+}
+
+// Conversions to primitive integer types, as an alternative to the as_* accessors that
+// says why the value didn't fit.
+macro_rules! integer_conversion {
+    ($target:ty, $wide_accessor:ident) => {
+        impl<'a, 'b> TryFrom<&'b Integer<'a>> for $target {
+            type Error = Error;
+
+            fn try_from(integer: &'b Integer<'a>) -> Result<$target, Error> {
+                let target = stringify!($target);
+                let wide = try!(integer.$wide_accessor().ok_or(integer.out_of_range(target)));
+                <$target>::try_from(wide).map_err(|_| integer.out_of_range(target))
+            }
+        }
+    }
+}
+
+integer_conversion!(u8, as_u64);
+integer_conversion!(u16, as_u64);
+integer_conversion!(u32, as_u64);
+integer_conversion!(u64, as_u64);
+integer_conversion!(i32, as_i64);
+integer_conversion!(i64, as_i64);
+
+fn bit_length_of<I: Iterator<Item=u8>>(bytes: I) -> usize {
+    let mut significant = bytes.skip_while(|x| *x == 0);
+    match significant.next() {
+        Some(first) => (8 - first.leading_zeros() as usize) + 8*significant.count(),
+        None => 0,
+    }
 }
 
 #[cfg(test)]
 mod test {
     use ::Parser;
     use ::Asn1Value;
+    use super::Integer;
+    use error::Error;
+    use std::convert::TryFrom;
+    use std::time::Duration;
     
     #[test]
     fn integer() {
@@ -54,4 +241,188 @@ mod test {
             }
         }
     }
+    
+    #[test]
+    fn unsigned_accessors() {
+        let padded = Integer::new(&[0x00, 0xff]);
+        assert_eq!(padded.as_u8(), Some(255));
+        assert_eq!(padded.as_u32(), Some(255));
+        assert_eq!(padded.as_u64(), Some(255));
+        
+        let minus_one = Integer::new(&[0xff]);
+        assert_eq!(minus_one.as_u8(), None);
+        assert_eq!(minus_one.as_u32(), None);
+        assert_eq!(minus_one.as_u64(), None);
+        
+        let minus_big = Integer::new(&[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(minus_big.as_u32(), None);
+        
+        let max_u32 = Integer::new(&[0x00, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(max_u32.as_u32(), Some(0xffffffff));
+        assert_eq!(max_u32.as_u8(), None);
+        
+        assert_eq!(Integer::new(&[0x00]).as_u8(), Some(0));
+        assert_eq!(Integer::new(&[]).as_u8(), None);
+    }
+    
+    #[test]
+    fn signed_accessors() {
+        assert_eq!(Integer::new(&[0x00]).as_i64(), Some(0));
+        assert_eq!(Integer::new(&[0x7f]).as_i64(), Some(127));
+        assert_eq!(Integer::new(&[0x00, 0x80]).as_i64(), Some(128));
+        assert_eq!(Integer::new(&[0xff]).as_i64(), Some(-1));
+        assert_eq!(Integer::new(&[0x80]).as_i64(), Some(-128));
+        assert_eq!(Integer::new(&[0xff, 0x7f]).as_i64(), Some(-129));
+        assert_eq!(Integer::new(&[0xff, 0xff, 0x7f]).as_i64(), Some(-129));
+        assert_eq!(Integer::new(&[0x80, 0, 0, 0, 0, 0, 0, 0]).as_i64(), Some(i64::MIN));
+        assert_eq!(Integer::new(&[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0]).as_i64(), None);
+        assert_eq!(Integer::new(&[]).as_i64(), None);
+        
+        assert_eq!(Integer::new(&[0x7f, 0xff, 0xff, 0xff]).as_i32(), Some(i32::MAX));
+        assert_eq!(Integer::new(&[0x00, 0x80, 0x00, 0x00, 0x00]).as_i32(), None);
+        assert_eq!(Integer::new(&[0x80, 0x00, 0x00, 0x00]).as_i32(), Some(i32::MIN));
+    }
+    
+    #[test]
+    fn conversions() {
+        let big = Integer::new(&[0x01, 0x00, 0x00]);
+        assert_eq!(u32::try_from(&big), Ok(0x10000));
+        assert_eq!(i64::try_from(&big), Ok(0x10000));
+        assert_eq!(u16::try_from(&big), Err(Error::IntegerOutOfRange{ target: "u16", negative: false }));
+        
+        let minus_one = Integer::new(&[0xff]);
+        assert_eq!(i32::try_from(&minus_one), Ok(-1));
+        assert_eq!(u8::try_from(&minus_one), Err(Error::IntegerOutOfRange{ target: "u8", negative: true }));
+        assert_eq!(u64::try_from(&minus_one), Err(Error::IntegerOutOfRange{ target: "u64", negative: true }));
+        
+        let very_negative = Integer::new(&[0x80, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(i32::try_from(&very_negative), Err(Error::IntegerOutOfRange{ target: "i32", negative: true }));
+        assert_eq!(i64::try_from(&very_negative), Ok(-0x80_0000_0000));
+        
+        let huge = Integer::new(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(u64::try_from(&huge), Ok(u64::MAX));
+        assert_eq!(i64::try_from(&huge), Err(Error::IntegerOutOfRange{ target: "i64", negative: false }));
+        assert_eq!(u8::try_from(&Integer::new(&[0x00, 0xff])), Ok(255));
+    }
+    
+    #[test]
+    fn minimal_encoding() {
+        assert!(Integer::new(&[0x00, 0x80]).is_minimally_encoded());
+        assert!(!Integer::new(&[0x00, 0x7f]).is_minimally_encoded());
+        assert!(Integer::new(&[0xff, 0x7f]).is_minimally_encoded());
+        assert!(!Integer::new(&[0xff, 0x80]).is_minimally_encoded());
+        assert!(Integer::new(&[0x00]).is_minimally_encoded());
+        assert!(!Integer::new(&[0x00, 0x00]).is_minimally_encoded());
+        assert!(!Integer::new(&[]).is_minimally_encoded());
+    }
+    
+    #[test]
+    fn zero() {
+        assert!(Integer::new(&[0x00]).is_zero());
+        assert!(Integer::new(&[0x00, 0x00]).is_zero());
+        assert!(!Integer::new(&[0x01]).is_zero());
+        assert!(!Integer::new(&[0x00, 0x01]).is_zero());
+        assert!(!Integer::new(&[0x80, 0x00]).is_zero());
+        assert!(!Integer::new(&[]).is_zero());
+    }
+    
+    #[test]
+    fn bit_length() {
+        assert_eq!(Integer::new(&[0x00]).bit_length(), 0);
+        assert_eq!(Integer::new(&[0x01]).bit_length(), 1);
+        assert_eq!(Integer::new(&[0x00, 0x80]).bit_length(), 8);
+        assert_eq!(Integer::new(&[0x01, 0x00, 0x01]).bit_length(), 17);
+        assert_eq!(Integer::new(&[0x7f, 0xff]).bit_length(), 15);
+        
+        let mut modulus = vec![0xc5; 257];
+        modulus[0] = 0x00;
+        assert_eq!(Integer::new(&modulus).bit_length(), 2048);
+        
+        assert_eq!(Integer::new(&[0xff]).bit_length(), 1); // -1
+        assert_eq!(Integer::new(&[0xfe]).bit_length(), 2); // -2
+        assert_eq!(Integer::new(&[0x80]).bit_length(), 8); // -128
+        assert_eq!(Integer::new(&[0xff, 0x7f]).bit_length(), 8); // -129
+        assert_eq!(Integer::new(&[0xff, 0x00]).bit_length(), 9); // -256
+        assert_eq!(Integer::new(&[0x80, 0x00]).bit_length(), 16); // -32768
+    }
+    
+    #[test]
+    fn bits() {
+        let five = Integer::new(&[0x05]);
+        let set: Vec<usize> = (0..8).filter(|n| five.test_bit(*n)).collect();
+        assert_eq!(set, [0, 2]);
+        assert!(!five.test_bit(100));
+        
+        let minus_one = Integer::new(&[0xff]);
+        assert!((0..8).all(|n| minus_one.test_bit(n)));
+        assert!(minus_one.test_bit(100));
+        
+        let padded = Integer::new(&[0x00, 0x80, 0x01]);
+        assert!(padded.test_bit(0) && padded.test_bit(15));
+        assert!(!padded.test_bit(16) && !padded.test_bit(64));
+    }
+    
+    #[test]
+    fn chars() {
+        assert_eq!(Integer::new(&[0x41]).as_char(), Some('A'));
+        assert_eq!(Integer::new(&[0x01, 0xf6, 0x00]).as_char(), Some('\u{1f600}'));
+        assert_eq!(Integer::new(&[0x00, 0xd8, 0x00]).as_char(), None); // a surrogate
+        assert_eq!(Integer::new(&[0x11, 0x00, 0x00]).as_char(), None);
+        assert_eq!(Integer::new(&[0xff]).as_char(), None);
+    }
+    
+    #[test]
+    fn duration() {
+        assert_eq!(Integer::new(&[0x0e, 0x10]).as_duration(), Some(Duration::from_secs(3600)));
+        assert_eq!(Integer::new(&[0x00]).as_duration(), Some(Duration::from_secs(0)));
+        assert_eq!(Integer::new(&[0xff]).as_duration(), None);
+        assert_eq!(Integer::new(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0]).as_duration(), None);
+    }
+    
+    #[test]
+    fn approximate_f64() {
+        assert_eq!(Integer::new(&[0x00]).as_f64(), 0.0);
+        assert_eq!(Integer::new(&[0x7f]).as_f64(), 127.0);
+        assert_eq!(Integer::new(&[0x00, 0x80]).as_f64(), 128.0);
+        assert_eq!(Integer::new(&[0x01, 0x00]).as_f64(), 256.0);
+        assert_eq!(Integer::new(&[0xff]).as_f64(), -1.0);
+        assert_eq!(Integer::new(&[0x80]).as_f64(), -128.0);
+        assert_eq!(Integer::new(&[0xff, 0x7f]).as_f64(), -129.0);
+        assert_eq!(Integer::new(&[0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).as_f64(), 9223372036854775807.0);
+        
+        let huge = [0x7f; 200];
+        assert_eq!(Integer::new(&huge).as_f64(), f64::INFINITY);
+        let huge_negative = [0x80; 200];
+        assert_eq!(Integer::new(&huge_negative).as_f64(), f64::NEG_INFINITY);
+    }
+    
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn rsa_modulus() {
+        // The modulus of a 2048-bit RSA test key.
+        let bs = [0x02, 0x82, 0x01, 0x01, 0x00, 0xb2, 0x62, 0x60, 0xfa, 0xa5, 0x5e, 0xb4, 0xc3, 0xb6, 0x22, 0xec,
+                  0xc4, 0x9a, 0x9e, 0xf2, 0x88, 0x51, 0x45, 0x5e, 0xaa, 0x42, 0x2e, 0x68, 0x7e, 0x6c, 0x0a, 0x22,
+                  0xa9, 0xc0, 0x53, 0xee, 0xc6, 0x4f, 0x81, 0x8f, 0xaf, 0xa5, 0x9a, 0x3a, 0x31, 0x4c, 0xdd, 0x1f,
+                  0x1c, 0x5b, 0x18, 0x99, 0x7e, 0xb7, 0x74, 0x2d, 0x39, 0x5b, 0x48, 0xae, 0xcd, 0x6d, 0x16, 0xbe,
+                  0xfd, 0x63, 0x5b, 0xfd, 0xe9, 0x3f, 0xbc, 0x8a, 0xbf, 0xee, 0xd1, 0xa9, 0x81, 0x86, 0x69, 0x8f,
+                  0x0e, 0x0b, 0xda, 0x4e, 0x7b, 0x60, 0xab, 0xd3, 0x40, 0xf6, 0xd3, 0x58, 0x72, 0xd8, 0xe5, 0xb9,
+                  0x3e, 0x94, 0xfc, 0x12, 0xc0, 0xfd, 0xcf, 0x51, 0x35, 0xe4, 0x3e, 0x74, 0x77, 0xda, 0xe3, 0x3f,
+                  0xe2, 0x27, 0xf6, 0xe3, 0xec, 0x5f, 0xaf, 0xcc, 0x97, 0x38, 0x3f, 0xd8, 0xa6, 0xe6, 0x4f, 0xb6,
+                  0xa4, 0x98, 0x1d, 0x86, 0xed, 0x03, 0xa9, 0xd6, 0xa1, 0x33, 0x21, 0xc3, 0x8b, 0x9b, 0xf2, 0x25,
+                  0x3e, 0x1b, 0x23, 0xa6, 0x86, 0x8b, 0x77, 0x29, 0x9b, 0xb3, 0x8f, 0xc8, 0xa5, 0xc6, 0x93, 0x4b,
+                  0x7d, 0x63, 0x31, 0xbe, 0x2f, 0xeb, 0x99, 0xce, 0x92, 0xcb, 0x1b, 0xcf, 0xd3, 0xfc, 0x68, 0xb0,
+                  0x41, 0x24, 0x8d, 0x40, 0x26, 0x2f, 0xdc, 0x48, 0xe9, 0x20, 0x44, 0x7b, 0x9c, 0xa2, 0x4e, 0x7e,
+                  0x78, 0x4f, 0x02, 0x9b, 0x8d, 0xda, 0xb0, 0x60, 0x95, 0x98, 0x3e, 0x7e, 0x4d, 0xb6, 0xb9, 0xbf,
+                  0xfd, 0x12, 0x21, 0xd7, 0xac, 0xdd, 0x73, 0x97, 0xae, 0x88, 0x38, 0xb7, 0xc5, 0x2a, 0xfc, 0x32,
+                  0x19, 0x08, 0xb3, 0xcc, 0xfd, 0x15, 0x61, 0xbe, 0xd7, 0x3e, 0x9d, 0x30, 0x8d, 0x35, 0x46, 0x63,
+                  0x96, 0xb1, 0xef, 0xa0, 0x59, 0x8c, 0x23, 0xba, 0x19, 0x40, 0x6d, 0xfe, 0xf8, 0x08, 0x3c, 0x38,
+                  0x89, 0x04, 0xd2, 0x78, 0x39];
+        let modulus = Parser::new(&bs).next_integer().unwrap().as_rsa_modulus().unwrap();
+        assert_eq!(modulus.bits(), 2048);
+        assert_eq!(modulus.to_bytes_be(), &bs[5..]);
+        
+        assert_eq!(Integer::new(&[0x00, 0x80]).as_rsa_modulus().unwrap().to_bytes_be(), [0x80]);
+        assert_eq!(Integer::new(&[0x80, 0x01]).as_rsa_modulus(), Err(Error::NegativeModulus));
+        assert_eq!(Integer::new(&[]).as_rsa_modulus(), Err(Error::Malformed));
+    }
 }
@@ -1,18 +1,70 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
+// The crate targets the 2015 edition and uses `try!` throughout, which is deprecated, as is
+// the `std::usize` module. The clippy lints below flag idioms from the original code, so
+// they are allowed rather than rewriting that code to pass `clippy -D warnings`.
+// `manual_is_multiple_of` suggests a method that is too new for the Rust versions the crate
+// supports.
+#![allow(deprecated)]
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::should_implement_trait,
+         clippy::manual_range_contains, clippy::map_clone, clippy::assign_op_pattern,
+         clippy::legacy_numeric_constants, clippy::assertions_on_constants, clippy::needless_borrow,
+         clippy::manual_is_multiple_of)]
+
+#[cfg(feature = "bigint")]
+extern crate num_bigint;
+#[cfg(feature = "bitflags")]
+#[cfg_attr(test, macro_use)]
+extern crate bitflags;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(test)]
+extern crate proptest;
 
 pub mod integer;
 pub mod object_identifier;
 pub mod error;
 pub mod printable_string;
+pub mod document;
+pub mod encoder;
+pub mod tag;
+pub mod bit_string;
+pub mod algorithm;
+pub mod tlv;
+pub mod time;
+pub mod pki;
+pub mod encoding;
+pub mod x509;
+pub mod cms;
+pub mod octet_string;
+pub mod real;
+pub mod owned;
+pub mod pem;
+#[cfg(test)]
+mod round_trip;
 
 use integer::Integer;
 use object_identifier::ObjectIdentifier;
+use bit_string::BitString;
+use time::DateTime;
 use error::Error;
 use printable_string::to_printable_string;
+use encoder::{der_set_cmp, write_length, write_tlv};
+
+pub use document::Document;
+pub use encoder::Writer;
+pub use tag::{Tag, TagClass};
 
+use std::any::Any;
+use std::cmp;
 use std::usize;
 use std::str;
+use std::convert::TryFrom;
+
+/// How deeply structures may nest in the functions that recurse into them (such as
+/// `tlv::parse_tlv_tree`), rather than streaming through `Parser`. Deeper input gives
+/// `NestingTooDeep` instead of overflowing the stack.
+pub const MAX_NESTING_DEPTH: usize = 64;
 
 fn usize_bytes() -> usize {
     // TODO: once usize::BYTES is stabilized, we can use that
@@ -25,30 +77,277 @@ fn usize_bytes() -> usize {
     count
 }
 
+/// Decodes the length octets at the start of `bytes`, returning the length along with the
+/// number of bytes it occupied.
+fn decode_length(bytes: &[u8]) -> Result<(usize, usize), Error> {
+    let initial = match bytes.first() {
+        Some(initial) => *initial,
+        None => { return Err(Error::EOF); }
+    };
+    
+    if initial < 0x80 {
+        return Ok((initial as usize, 1));
+    }
+    
+    if initial == 0xff {
+        return Err(Error::ReservedLength); // reserved for future extensions by X.690
+    }
+    
+    let length_byte_count = (initial & 0x7f) as usize;
+    
+    if length_byte_count > usize_bytes()  {
+        return Err(Error::OverlongLength);
+    }
+    
+    let length_bytes = match bytes.get(1 .. 1 + length_byte_count) {
+        Some(length_bytes) => length_bytes,
+        None => { return Err(Error::EOF); }
+    };
+    let mut length_bytes_iter = length_bytes.iter();
+    
+    let mut length_accumulator = if let Some(length_msb) = length_bytes_iter.next() {
+        if *length_msb == 0 {
+            // The most significant byte being 0 means this uses needlessly many bytes.
+            return Err(Error::InvalidLengthEncoding);
+        }
+        *length_msb as usize
+    } else {
+        return Err(Error::InvalidLengthEncoding);
+    };
+    
+    for length_byte in length_bytes_iter {
+        length_accumulator = (length_accumulator<<8) | (*length_byte as usize);
+    }
+    
+    if length_accumulator < 128 {
+        return Err(Error::InvalidLengthEncoding); // should have used the one-byte form
+    }
+    
+    Ok((length_accumulator, 1 + length_byte_count))
+}
+
+/// Decodes a length in any of BER's forms, returning None for the indefinite form, along
+/// with the number of bytes used and whether the encoding was the minimal one.
+fn decode_ber_length(bytes: &[u8]) -> Result<(Option<usize>, usize, bool), Error> {
+    let initial = match bytes.first() {
+        Some(initial) => *initial,
+        None => { return Err(Error::EOF); }
+    };
+    
+    if initial < 0x80 {
+        return Ok((Some(initial as usize), 1, true));
+    }
+    if initial == 0x80 {
+        return Ok((None, 1, true));
+    }
+    if initial == 0xff {
+        return Err(Error::ReservedLength);
+    }
+    
+    let length_bytes = match bytes.get(1 .. 1 + (initial & 0x7f) as usize) {
+        Some(length_bytes) => length_bytes,
+        None => { return Err(Error::EOF); }
+    };
+    
+    // Leading zero bytes are allowed in BER, so only the significant ones need to fit.
+    let significant = &length_bytes[length_bytes.iter().take_while(|x| **x == 0).count()..];
+    if significant.len() > usize_bytes() {
+        return Err(Error::OverlongLength);
+    }
+    let length = significant.iter().fold(0usize, |accum, x| (accum << 8) | (*x as usize));
+    
+    let minimal = significant.len() == length_bytes.len() && length >= 0x80;
+    Ok((Some(length), 1 + length_bytes.len(), minimal))
+}
+
+/// Whatever a custom tag handler makes of a value's content. It must be `Send + Sync` so
+/// that `Asn1Value` still is.
+pub type CustomValue = Box<dyn Any + Send + Sync>;
+
+/// Decodes the content of a value with a custom tag. See `Parser::set_custom_handler`.
+pub type CustomHandler = Box<dyn Fn(&[u8]) -> Result<CustomValue, Error> + Send + Sync>;
+
+// Parsers and values can be moved and shared across threads, custom handlers or not.
+fn assert_send_sync() {
+    fn check<T: Send + Sync>() {}
+    check::<Parser>();
+    check::<Asn1Value>();
+}
 
 pub enum Asn1Value<'a> {
     Null,
     Boolean(bool),
     Integer(Integer<'a>),
+    Enumerated(Integer<'a>),
+    Real(f64),
+    BitString(BitString<'a>),
     ObjectIdentifier(ObjectIdentifier<'a>),
     OctetString(&'a [u8]),
     PrintableString(&'a str),
     Utf8String(&'a str),
+    Ia5String(&'a str),
+    /// A BMPString, decoded from UCS-2. It can't borrow from the input, unlike other strings.
+    BmpString(String),
+    /// A UniversalString, decoded from UCS-4. Like a BMPString, it's decoded into a copy.
+    UniversalString(String),
+    /// The raw content of a GeneralString. Its character sets are switched with ISO 2022
+    /// escapes, which aren't interpreted here, so the caller must decode it.
+    GeneralString(&'a [u8]),
+    UtcTime(DateTime),
+    GeneralizedTime(DateTime),
     SequenceStart,
     SequenceEnd,
     SetStart,
     SetEnd,
+    /// The start of a constructed value with a non-universal tag, such as an EXPLICIT
+    /// `[0]`. Its content follows as ordinary values, ended by a TaggedEnd.
+    TaggedStart(TagClass, u32),
+    TaggedEnd,
+    /// A primitive value with a non-universal tag. Since the tag doesn't say what the
+    /// underlying type is, the content is left uninterpreted.
+    Tagged(TagClass, u32, &'a [u8]),
+    /// A primitive universal value of a type this parser doesn't know, returned only when
+    /// unknown types are tolerated.
+    Unknown { tag: u8, content: &'a [u8] },
+    /// The start of a constructed universal value of a type this parser doesn't know,
+    /// returned only when unknown types are tolerated. Ended by an UnknownEnd.
+    UnknownStart { tag: u8 },
+    UnknownEnd,
+    /// A value decoded by a custom tag handler. Since its content isn't kept, it can't be
+    /// encoded again.
+    Custom { tag: u8, value: CustomValue },
+}
+
+impl<'a> Asn1Value<'a> {
+    /// The ASN.1 name of the value's type, such as "INTEGER" or "PrintableString", for
+    /// diagnostics. A structure's start and end markers both give the structure's type.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Asn1Value::Null => "NULL",
+            Asn1Value::Boolean(_) => "BOOLEAN",
+            Asn1Value::Integer(_) => "INTEGER",
+            Asn1Value::Enumerated(_) => "ENUMERATED",
+            Asn1Value::Real(_) => "REAL",
+            Asn1Value::BitString(_) => "BIT STRING",
+            Asn1Value::ObjectIdentifier(_) => "OBJECT IDENTIFIER",
+            Asn1Value::OctetString(_) => "OCTET STRING",
+            Asn1Value::PrintableString(_) => "PrintableString",
+            Asn1Value::Utf8String(_) => "UTF8String",
+            Asn1Value::Ia5String(_) => "IA5String",
+            Asn1Value::BmpString(_) => "BMPString",
+            Asn1Value::UniversalString(_) => "UniversalString",
+            Asn1Value::GeneralString(_) => "GeneralString",
+            Asn1Value::UtcTime(_) => "UTCTime",
+            Asn1Value::GeneralizedTime(_) => "GeneralizedTime",
+            Asn1Value::SequenceStart | Asn1Value::SequenceEnd => "SEQUENCE",
+            Asn1Value::SetStart | Asn1Value::SetEnd => "SET",
+            Asn1Value::TaggedStart(..) | Asn1Value::TaggedEnd | Asn1Value::Tagged(..) => "tagged value",
+            Asn1Value::Unknown{ .. } | Asn1Value::UnknownStart{ .. } | Asn1Value::UnknownEnd => "unknown type",
+            Asn1Value::Custom{ .. } => "custom value",
+        }
+    }
+
+    /// Appends the DER encoding of this value. Only complete values can be encoded; the
+    /// structure start and end markers return `UnencodableValue`.
+    pub fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        match *self {
+            Asn1Value::Null => write_tlv(out, 0x05, &[]),
+            Asn1Value::Boolean(x) => write_tlv(out, 0x01, &[if x { 0xff } else { 0x00 }]),
+            Asn1Value::Integer(ref x) => write_tlv(out, 0x02, x.as_bytes()),
+            Asn1Value::Enumerated(ref x) => write_tlv(out, 0x0A, x.as_bytes()),
+            Asn1Value::BitString(ref x) => {
+                out.push(0x03);
+                write_length(out, 1 + x.data().len());
+                out.push(x.unused_bits());
+                out.extend_from_slice(x.data());
+            }
+            Asn1Value::ObjectIdentifier(ref x) => write_tlv(out, 0x06, x.as_bytes()),
+            Asn1Value::OctetString(x) => write_tlv(out, 0x04, x),
+            Asn1Value::PrintableString(x) => write_tlv(out, 0x13, x.as_bytes()),
+            Asn1Value::Utf8String(x) => write_tlv(out, 0x0C, x.as_bytes()),
+            Asn1Value::Ia5String(x) => write_tlv(out, 0x16, x.as_bytes()),
+            Asn1Value::BmpString(ref x) => {
+                let ucs2: Vec<u8> = x.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect();
+                if ucs2.len() != x.chars().count() * 2 {
+                    return Err(Error::UnencodableValue); // outside the Basic Multilingual Plane
+                }
+                write_tlv(out, 0x1E, &ucs2)
+            }
+            Asn1Value::UniversalString(ref x) => {
+                let ucs4: Vec<u8> = x.chars().flat_map(|c| (c as u32).to_be_bytes()).collect();
+                write_tlv(out, 0x1C, &ucs4)
+            }
+            Asn1Value::GeneralString(x) => write_tlv(out, 0x1B, x),
+            Asn1Value::Real(x) => {
+                let mut content = Vec::new();
+                try!(real::encode_real(x, &mut content));
+                write_tlv(out, 0x09, &content)
+            }
+            Asn1Value::UtcTime(ref x) => write_tlv(out, 0x17, try!(x.to_utc_time()).as_bytes()),
+            Asn1Value::GeneralizedTime(ref x) => write_tlv(out, 0x18, try!(x.to_generalized_time()).as_bytes()),
+            Asn1Value::Tagged(class, number, content) => {
+                Tag{ class: class, constructed: false, number: number }.encode(out);
+                write_length(out, content.len());
+                out.extend_from_slice(content);
+            }
+            Asn1Value::Unknown{ tag, content } => write_tlv(out, tag, content),
+            Asn1Value::SequenceStart | Asn1Value::SequenceEnd |
+            Asn1Value::SetStart | Asn1Value::SetEnd |
+            Asn1Value::TaggedStart(..) | Asn1Value::TaggedEnd |
+            Asn1Value::UnknownStart{ .. } | Asn1Value::UnknownEnd |
+            Asn1Value::Custom{ .. } => {
+                return Err(Error::UnencodableValue);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> TryFrom<Asn1Value<'a>> for bool {
+    type Error = Error;
+
+    fn try_from(value: Asn1Value<'a>) -> Result<bool, Error> {
+        match value {
+            Asn1Value::Boolean(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+}
+
+impl<'a> TryFrom<Asn1Value<'a>> for &'a str {
+    type Error = Error;
+
+    fn try_from(value: Asn1Value<'a>) -> Result<&'a str, Error> {
+        match value {
+            Asn1Value::PrintableString(x) | Asn1Value::Utf8String(x) | Asn1Value::Ia5String(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+}
+
+impl<'a> TryFrom<Asn1Value<'a>> for &'a [u8] {
+    type Error = Error;
+
+    fn try_from(value: Asn1Value<'a>) -> Result<&'a [u8], Error> {
+        match value {
+            Asn1Value::OctetString(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 enum StructureKind {
     Sequence,
     Set,
+    Tagged(TagClass, u32),
+    Unknown(u8),
 }
 
 #[derive(Debug, Copy, Clone)]
 struct Structure {
     kind: StructureKind,
+    tag: Tag,
     end_position: usize,
 }
 
@@ -56,6 +355,22 @@ pub struct Parser<'a> {
     input: &'a [u8],
     position: usize,
     structures: Vec<Structure>,
+    // The end_position of the last entry in structures (or the end of the input if there are
+    // none), kept here since it's needed for every value.
+    innermost_end: usize,
+    last_tag: Option<Tag>,
+    tolerate_unknown: bool,
+    strict_bit_string: bool,
+    allow_ber: bool,
+    reject_encoded_defaults: bool,
+    reject_control_chars: bool,
+    strict_length: bool,
+    // The offsets of non-minimal lengths that were accepted because strict_length was off.
+    length_warnings: Vec<usize>,
+    custom_handlers: Vec<(u8, CustomHandler)>,
+    skip_interdocument_padding: bool,
+    max_values: usize,
+    values_read: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -64,64 +379,273 @@ impl<'a> Parser<'a> {
             input: input,
             position: 0,
             structures: Vec::new(),
+            innermost_end: input.len(),
+            last_tag: None,
+            tolerate_unknown: false,
+            strict_bit_string: true,
+            allow_ber: false,
+            reject_encoded_defaults: false,
+            reject_control_chars: false,
+            strict_length: true,
+            length_warnings: Vec::new(),
+            custom_handlers: Vec::new(),
+            skip_interdocument_padding: false,
+            max_values: usize::MAX,
+            values_read: 0,
         }
     }
 
-    fn read_length(&mut self) -> Result<usize, Error> {
-        let initial = try!(self.consume_one());
-        
-        if initial < 0x80 {
-            return Ok(initial as usize);
+    /// Creates a parser that won't look at more than `max_total` bytes of `input`, even if
+    /// the slice is longer. Anything claiming to extend past that point fails as though the
+    /// input ended there.
+    pub fn with_limit(input: &'a [u8], max_total: usize) -> Parser<'a> {
+        Parser::new(&input[..cmp::min(input.len(), max_total)])
+    }
+
+    /// Reads the header of the single value at the start of `input`, returning a parser over
+    /// just that value's content, along with whatever follows the value.
+    pub fn new_framed(input: &'a [u8]) -> Result<(Parser<'a>, &'a [u8]), Error> {
+        let (_, tag_size) = try!(Tag::decode(input));
+        let (length, length_size) = try!(decode_length(&input[tag_size..]));
+        let content_start = tag_size + length_size;
+        if length > input.len() - content_start {
+            return Err(Error::EOF);
         }
         
-        let length_byte_count = (initial & 0x7f) as usize;
-        
-        if length_byte_count > usize_bytes()  {
-            return Err(Error::OverlongLength);
+        let content_end = content_start + length;
+        Ok((Parser::new(&input[content_start..content_end]), &input[content_end..]))
+    }
+
+    /// The number of bytes consumed so far. A sub-parser (such as one from
+    /// `take_rest_of_structure`) counts from the start of its own content, not the parent's.
+    pub fn consumed(&self) -> usize {
+        self.position
+    }
+
+    /// Gives up the parser, returning its input and the position it had reached in it.
+    pub fn into_inner(self) -> (&'a [u8], usize) {
+        (self.input, self.position)
+    }
+
+    /// Splits the next top-level value (header included) off into a parser of its own, for
+    /// inputs that are several documents concatenated together. Returns `None` once the
+    /// input is used up, and `UnbalancedStructure` if called from inside a structure.
+    pub fn next_document(&mut self) -> Result<Option<Parser<'a>>, Error> {
+        if !self.structures.is_empty() {
+            return Err(Error::UnbalancedStructure);
         }
-        
-        let length_bytes = try!(self.consume(length_byte_count));
-        let mut length_bytes_iter = length_bytes.iter();
-        
-        let mut length_accumulator = if let Some(length_msb) = length_bytes_iter.next() {
-            if *length_msb == 0 {
-                // The most significant byte being 0 means this uses needlessly many bytes.
-                return Err(Error::InvalidLengthEncoding);
+        if self.skip_interdocument_padding {
+            while self.input.get(self.position) == Some(&0x00) {
+                self.position += 1;
             }
-            *length_msb as usize
-        } else {
-            return Err(Error::InvalidLengthEncoding);
-        };
+        }
+        if self.position == self.input.len() {
+            return Ok(None);
+        }
         
-        for length_byte in length_bytes_iter {
-            length_accumulator = (length_accumulator<<8) | (*length_byte as usize);
+        let start = self.position;
+        let (_, tag_size) = try!(Tag::decode(&self.input[start..]));
+        let (length, length_size, _) = try!(self.decode_length_at(&self.input[start + tag_size..]));
+        let content_start = start + tag_size + length_size;
+        if length > self.input.len() - content_start {
+            return Err(Error::EOF);
         }
         
-        if length_accumulator < 128 {
-            return Err(Error::InvalidLengthEncoding); // should have used the one-byte form
+        self.position = content_start + length;
+        Ok(Some(Parser::new(&self.input[start..self.position])))
+    }
+
+    /// When enabled, `next_document` skips any 0x00 bytes between documents, which some
+    /// tools emit when concatenating them. Disabled by default, in which case such a byte is
+    /// read as the start of a (malformed) document.
+    pub fn set_skip_interdocument_padding(&mut self, skip_interdocument_padding: bool) {
+        self.skip_interdocument_padding = skip_interdocument_padding;
+    }
+
+    /// Limits the number of values (not counting structures' end markers) that `next` will
+    /// produce, after which it fails with `TooManyValues`. Calls that fail don't count. This
+    /// bounds the memory used by anything collecting every value of untrusted input.
+    /// Unlimited by default.
+    pub fn set_max_values(&mut self, max_values: usize) {
+        self.max_values = max_values;
+    }
+
+    /// When enabled, universal types that this parser doesn't recognize are returned as
+    /// `Asn1Value::Unknown` (or `UnknownStart`, for constructed ones) rather than causing an
+    /// `UnrecognizedType` error. Disabled by default.
+    pub fn set_tolerate_unknown(&mut self, tolerate_unknown: bool) {
+        self.tolerate_unknown = tolerate_unknown;
+    }
+
+    /// When enabled (the default), a BIT STRING whose unused trailing bits aren't zero is
+    /// rejected with `InvalidBitString`, as DER requires. Disabling this accepts them.
+    pub fn set_strict_bit_string(&mut self, strict_bit_string: bool) {
+        self.strict_bit_string = strict_bit_string;
+    }
+
+    /// When enabled, BER encodings that DER forbids (such as constructed strings) are
+    /// accepted where the parser knows how to handle them. Disabled by default.
+    pub fn set_allow_ber(&mut self, allow_ber: bool) {
+        self.allow_ber = allow_ber;
+    }
+
+    /// When enabled, a DEFAULT value that is present despite equaling its default is rejected
+    /// with `DefaultValueEncoded`, since DER requires it to be omitted. Disabled by default.
+    pub fn set_reject_encoded_defaults(&mut self, reject_encoded_defaults: bool) {
+        self.reject_encoded_defaults = reject_encoded_defaults;
+    }
+
+    /// When enabled, a UTF8String containing control characters (C0, DEL or C1) other than
+    /// tab and newline is rejected with `DisallowedControlCharacter`. Disabled by default.
+    pub fn set_reject_control_chars(&mut self, reject_control_chars: bool) {
+        self.reject_control_chars = reject_control_chars;
+    }
+
+    /// When enabled (the default), a length that isn't encoded in the fewest bytes possible
+    /// is rejected with `InvalidLengthEncoding`, as DER requires. Disabling this accepts
+    /// them, recording where they were in `length_warnings`. That includes lengths padded
+    /// out to more bytes than a usize has, as long as the value itself fits.
+    pub fn set_strict_length(&mut self, strict_length: bool) {
+        self.strict_length = strict_length;
+    }
+
+    /// The offsets of any non-minimal lengths accepted while `strict_length` was disabled.
+    pub fn length_warnings(&self) -> &[usize] {
+        &self.length_warnings
+    }
+
+    /// Registers `handler` to decode the content of values whose (single-byte) tag is
+    /// `tag`, giving an `Asn1Value::Custom`. It's only used for tags that aren't otherwise
+    /// recognized: non-universal ones, and universal ones this parser doesn't handle.
+    pub fn set_custom_handler(&mut self, tag: u8, handler: CustomHandler) {
+        self.custom_handlers.retain(|&(existing, _)| existing != tag);
+        self.custom_handlers.push((tag, handler));
+    }
+
+    fn has_custom_handler(&self, tag: Tag, tag_byte: u8) -> bool {
+        tag.number < 31 && self.custom_handlers.iter().any(|&(existing, _)| existing == tag_byte)
+    }
+
+    fn read_custom(&mut self, length: usize, tag_byte: u8) -> Result<Asn1Value<'a>, Error> {
+        let content = try!(self.consume(length));
+        let handler = match self.custom_handlers.iter().find(|&&(existing, _)| existing == tag_byte) {
+            Some(entry) => &entry.1,
+            None => { return Err(Error::UnrecognizedType); }
+        };
+        Ok(Asn1Value::Custom{ tag: tag_byte, value: try!(handler(content)) })
+    }
+
+    /// Decodes a length, returning whether it was minimally encoded along with the usual
+    /// length and size. Non-minimal lengths are only possible if strict_length is off.
+    fn decode_length_at(&self, bytes: &[u8]) -> Result<(usize, usize, bool), Error> {
+        if self.strict_length {
+            return decode_length(bytes).map(|(length, size)| (length, size, true));
         }
         
-        Ok(length_accumulator)
+        match try!(decode_ber_length(bytes)) {
+            (Some(length), size, minimal) => Ok((length, size, minimal)),
+            (None, _, _) => Err(Error::InvalidLengthEncoding), // indefinite lengths aren't supported
+        }
     }
 
-    fn consume_one(&mut self) -> Result<u8, Error> {
-        match self.input.get(self.position) {
-            Some(x) => {
-                self.position += 1;
-                Ok(*x)
-            }
-            None => {
-                Err(Error::EOF)
+    /// Running out of bytes while reading a header means the input is truncated at the top
+    /// level, but that a structure's content is, within one.
+    fn header_error(&self, error: Error) -> Error {
+        if error == Error::EOF && !self.structures.is_empty() {
+            return Error::IncompleteStructureContent;
+        }
+        error
+    }
+
+    fn read_tag(&mut self) -> Result<Tag, Error> {
+        let (tag, tag_length) = try!(Tag::decode(self.remaining()).map_err(|e| self.header_error(e)));
+        self.position += tag_length;
+        Ok(tag)
+    }
+
+    fn read_length(&mut self) -> Result<usize, Error> {
+        let (length, length_size, minimal) = try!(self.decode_length_at(self.remaining())
+            .map_err(|e| self.header_error(e)));
+        if !minimal && !self.length_warnings.contains(&self.position) {
+            self.length_warnings.push(self.position);
+        }
+        self.position += length_size;
+        Ok(length)
+    }
+
+    fn push_structure(&mut self, structure: Structure) {
+        self.innermost_end = structure.end_position;
+        self.structures.push(structure);
+    }
+
+    fn pop_structure(&mut self) -> Option<Structure> {
+        let popped = self.structures.pop();
+        self.innermost_end = self.structures.last().map(|x| x.end_position).unwrap_or(self.input.len());
+        popped
+    }
+
+    /// The input from the current position up to the end of the innermost structure.
+    fn remaining(&self) -> &'a [u8] {
+        let end = self.innermost_end;
+        &self.input[self.position..end.max(self.position)]
+    }
+
+    /// Checks that `count` more bytes fit before the end of the innermost structure, so that
+    /// a value can't reach into whatever follows it.
+    fn check_available(&self, count: usize) -> Result<(), Error> {
+        // The somewhat strange logic is to avoid an overflow given a ridiculous count.
+        let end = self.innermost_end;
+        if count > end || end - count < self.position {
+            if self.structures.is_empty() {
+                return Err(Error::EOF);
             }
+            return Err(Error::StructureOverrun);
         }
+        Ok(())
     }
 
-    fn consume(&mut self, count: usize) -> Result<&[u8], Error> {
-        // Check that we have enough. The somewhat strange logic is to avoid an overflow given
-        // a ridiculous count.
-        if count > self.input.len() || self.input.len() - count < self.position {
-            return Err(Error::EOF);
+    /// Whether the innermost structure has no more values in it.
+    fn at_structure_end(&self) -> bool {
+        self.innermost_end == self.position && !self.structures.is_empty()
+    }
+
+    /// Decodes the tag of the next value without consuming anything. Returns
+    /// `EndOfStructure` if the innermost structure has no more values.
+    pub fn peek_tag(&self) -> Result<Tag, Error> {
+        if self.at_structure_end() {
+            return Err(Error::EndOfStructure);
+        }
+        Tag::decode(self.remaining()).map(|(tag, _)| tag)
+    }
+
+    /// Whether the next value is constructed (as opposed to primitive), without consuming
+    /// anything.
+    pub fn peek_constructed(&self) -> Result<bool, Error> {
+        self.peek_tag().map(|tag| tag.constructed)
+    }
+
+    /// Decodes the header of the next value without consuming anything, returning the
+    /// number of bytes taken by the tag and length, and the length of the content.
+    pub fn peek_length(&self) -> Result<(usize, usize), Error> {
+        if self.at_structure_end() {
+            return Err(Error::EndOfStructure);
         }
+        let remaining = self.remaining();
+        let (_, tag_size) = try!(Tag::decode(remaining));
+        let (length, length_size, _) = try!(self.decode_length_at(&remaining[tag_size..]));
+        Ok((tag_size + length_size, length))
+    }
+
+    fn consume_one(&mut self) -> Result<u8, Error> {
+        try!(self.check_available(1));
+        
+        let result = self.input[self.position];
+        self.position += 1;
+        Ok(result)
+    }
+
+    fn consume(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        try!(self.check_available(count));
         
         let result = &self.input[self.position .. self.position + count];
         
@@ -130,7 +654,7 @@ impl<'a> Parser<'a> {
         Ok(result)
     }
 
-    fn read_boolean(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_boolean(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         if length != 1 {
             return Err(Error::IncorrectLength);
         }
@@ -142,19 +666,24 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn read_integer(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_integer(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         Ok(Asn1Value::Integer( Integer::new(try!(self.consume(length)))) )
     }
 
-    fn read_bit_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
-        Err(Error::NotImplemented)
+    fn read_enumerated(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        Ok(Asn1Value::Enumerated( Integer::new(try!(self.consume(length)))) )
+    }
+
+    fn read_bit_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        let content = try!(self.consume(length));
+        Ok(Asn1Value::BitString( try!(BitString::with_padding_check(content, self.strict_bit_string)) ))
     }
 
-    fn read_octet_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_octet_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         Ok(Asn1Value::OctetString( try!(self.consume(length)) ))
     }
 
-    fn read_null(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_null(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         if length != 0 {
             return Err(Error::IncorrectLength);
         }
@@ -162,122 +691,712 @@ impl<'a> Parser<'a> {
         Ok(Asn1Value::Null)
     }
 
-    fn read_object_identifier(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_object_identifier(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         let oid_bytes = try!(self.consume(length));
         Ok(Asn1Value::ObjectIdentifier( try!(ObjectIdentifier::new(oid_bytes)) ))
     }
 
-    fn read_utf8_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn read_utf8_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         let utf8_bytes = try!(self.consume(length));
         let utf8_str = try!(str::from_utf8(utf8_bytes).map_err(|_| Error::InvalidUTF8));
+        try!(self.check_control_chars(utf8_str));
         Ok(Asn1Value::Utf8String( utf8_str ))
     }
 
-    fn read_printable_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
+    fn check_control_chars(&self, s: &str) -> Result<(), Error> {
+        if self.reject_control_chars && s.chars().any(|c| c.is_control() && c != '\t' && c != '\n') {
+            return Err(Error::DisallowedControlCharacter);
+        }
+        Ok(())
+    }
+
+    fn read_printable_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
         let bs = try!(self.consume(length));
         
         Ok(Asn1Value::PrintableString( try!(to_printable_string(bs)) ))
     }
 
-    fn read_ia5_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
-        Err(Error::NotImplemented)
+    fn read_real(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        let content = try!(self.consume(length));
+        Ok(Asn1Value::Real( try!(real::decode_real(content, !self.allow_ber)) ))
     }
 
-    fn read_bmp_string(&mut self, length: usize) -> Result<Asn1Value, Error> {
-        Err(Error::NotImplemented)
+    fn read_general_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        Ok(Asn1Value::GeneralString( try!(self.consume(length)) ))
     }
 
-    fn read_structure(&mut self, length: usize, kind: StructureKind) -> Result<Asn1Value, Error> {
-        let maximum_allowed_end = self.structures.last().map(|x| x.end_position).unwrap_or(self.input.len());
-        if length > maximum_allowed_end || self.position > maximum_allowed_end - length {
-            return Err(Error::EOF);
+    fn read_utc_time(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        let bs = try!(self.consume(length));
+        Ok(Asn1Value::UtcTime( try!(DateTime::from_utc_time(bs)) ))
+    }
+
+    fn read_generalized_time(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        let bs = try!(self.consume(length));
+        Ok(Asn1Value::GeneralizedTime( try!(DateTime::from_generalized_time(bs)) ))
+    }
+
+    fn read_ia5_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        let bs = try!(self.consume(length));
+        if !bs.is_ascii() {
+            return Err(Error::InvalidIA5String);
+        }
+        // ASCII is always valid UTF-8.
+        Ok(Asn1Value::Ia5String( try!(str::from_utf8(bs).map_err(|_| Error::InvalidIA5String)) ))
+    }
+
+    fn read_bmp_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        // Checked before consuming, so that a bad length fails without reading the content.
+        if length % 2 != 0 {
+            return Err(Error::InvalidBMPString);
+        }
+        let bs = try!(self.consume(length));
+        
+        // Each character is a single UCS-2 code unit, so surrogates aren't allowed.
+        let mut decoded = String::with_capacity(bs.len() / 2);
+        for unit in bs.chunks(2) {
+            let c = try!(char::from_u32(((unit[0] as u32) << 8) | unit[1] as u32).ok_or(Error::InvalidBMPString));
+            decoded.push(c);
+        }
+        Ok(Asn1Value::BmpString(decoded))
+    }
+
+    fn read_universal_string(&mut self, length: usize) -> Result<Asn1Value<'a>, Error> {
+        if length % 4 != 0 {
+            return Err(Error::InvalidUniversalString);
         }
+        let bs = try!(self.consume(length));
+        
+        let mut decoded = String::with_capacity(bs.len() / 4);
+        for unit in bs.chunks(4) {
+            let code_point = u32::from_be_bytes([unit[0], unit[1], unit[2], unit[3]]);
+            decoded.push(try!(char::from_u32(code_point).ok_or(Error::InvalidUniversalString)));
+        }
+        Ok(Asn1Value::UniversalString(decoded))
+    }
+
+    fn read_structure(&mut self, length: usize, tag: Tag, kind: StructureKind) -> Result<Asn1Value<'a>, Error> {
+        // This guarantees the end is within the input, so it can't overflow.
+        try!(self.check_available(length));
         
-        self.structures.push(Structure{
+        let end_position = self.position + length;
+        self.push_structure(Structure{
             kind: kind,
-            end_position: self.position + length,
+            tag: tag,
+            end_position: end_position,
         });
         
         Ok(match kind {
             StructureKind::Sequence => Asn1Value::SequenceStart,
             StructureKind::Set => Asn1Value::SetStart,
+            StructureKind::Tagged(class, number) => Asn1Value::TaggedStart(class, number),
+            StructureKind::Unknown(tag) => Asn1Value::UnknownStart{ tag: tag },
         })
     }
     
-    fn read_sequence(&mut self, length: usize) -> Result<Asn1Value, Error> {
-        self.read_structure(length, StructureKind::Sequence)
+    fn read_tagged(&mut self, length: usize, tag: Tag) -> Result<Asn1Value<'a>, Error> {
+        if tag.constructed {
+            return self.read_structure(length, tag, StructureKind::Tagged(tag.class, tag.number));
+        }
+        
+        Ok(Asn1Value::Tagged(tag.class, tag.number, try!(self.consume(length))))
+    }
+
+    fn read_unknown(&mut self, length: usize, tag: Tag, tag_byte: u8) -> Result<Asn1Value<'a>, Error> {
+        if !self.tolerate_unknown {
+            return Err(Error::UnrecognizedType);
+        }
+        
+        if tag.constructed {
+            return self.read_structure(length, tag, StructureKind::Unknown(tag_byte));
+        }
+        
+        Ok(Asn1Value::Unknown{ tag: tag_byte, content: try!(self.consume(length)) })
+    }
+
+    /// A constructed BIT STRING or OCTET STRING, which DER forbids. With BER allowed, it's
+    /// returned as a structure of its segments (`read_constructed_string` reassembles them).
+    fn read_constructed_bit_or_octet_string(&mut self, length: usize, tag: Tag, tag_byte: u8) -> Result<Asn1Value<'a>, Error> {
+        if !self.allow_ber {
+            return Err(Error::ConstructedMismatch);
+        }
+        self.read_structure(length, tag, StructureKind::Unknown(tag_byte))
     }
 
-    fn read_set(&mut self, length: usize) -> Result<Asn1Value, Error> {
-        self.read_structure(length, StructureKind::Set)
+    fn read_sequence(&mut self, length: usize, tag: Tag) -> Result<Asn1Value<'a>, Error> {
+        self.read_structure(length, tag, StructureKind::Sequence)
+    }
+
+    fn read_set(&mut self, length: usize, tag: Tag) -> Result<Asn1Value<'a>, Error> {
+        self.read_structure(length, tag, StructureKind::Set)
     }
     
-    pub fn next(&mut self) -> Result<Asn1Value, Error> {
-        if let Some(innermost_structure) = self.structures.last().map(|x| *x) {
-            if innermost_structure.end_position <= self.position {
-                if innermost_structure.end_position != self.position {
-                    return Err(Error::StructureOverrun);
+    /// Reads the start of a SET, checking that its members are in the ascending tag order
+    /// that DER requires (by class, then number). The parser is left positioned at the first
+    /// member. For a SET OF, whose members share a tag, use `expect_set_of` instead.
+    pub fn expect_set(&mut self) -> Result<(), Error> {
+        match try!(self.next()) {
+            Asn1Value::SetStart => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        
+        let start = self.position;
+        let result = self.check_set_order();
+        self.position = start;
+        result
+    }
+
+    /// Reads the start of a SET OF, checking that its members are in the ascending order
+    /// that DER requires. The parser is left positioned at the first member.
+    pub fn expect_set_of(&mut self) -> Result<(), Error> {
+        match try!(self.next()) {
+            Asn1Value::SetStart => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        
+        let start = self.position;
+        let result = self.check_set_of_order();
+        self.position = start;
+        result
+    }
+
+    fn skip_value(&mut self) -> Result<(), Error> {
+        try!(self.read_tag());
+        let length = try!(self.read_length());
+        try!(self.consume(length));
+        Ok(())
+    }
+
+    /// Reads the next value without interpreting it, returning its whole encoding (tag,
+    /// length and content). A constructed value is consumed along with everything in it.
+    /// This suits ANY fields that are to be stored, forwarded, or decoded later.
+    pub fn read_any(&mut self) -> Result<&'a [u8], Error> {
+        if self.at_structure_end() {
+            return Err(Error::EndOfStructure);
+        }
+        
+        let start = self.position;
+        if let Err(error) = self.skip_value() {
+            self.position = start;
+            return Err(error);
+        }
+        Ok(&self.input[start..self.position])
+    }
+
+    fn check_set_order(&mut self) -> Result<(), Error> {
+        let end = self.innermost_end;
+        let mut previous: Option<(TagClass, u32)> = None;
+        
+        while self.position < end {
+            let tag = try!(self.read_tag());
+            let length = try!(self.read_length());
+            try!(self.consume(length));
+            
+            let key = (tag.class, tag.number);
+            if previous.is_some_and(|previous| key <= previous) {
+                return Err(Error::SetNotSorted);
+            }
+            previous = Some(key);
+        }
+        
+        Ok(())
+    }
+
+    fn check_set_of_order(&mut self) -> Result<(), Error> {
+        let end = self.innermost_end;
+        let mut previous: Option<&'a [u8]> = None;
+        
+        while self.position < end {
+            let member_start = self.position;
+            try!(self.skip_value());
+            
+            let member = &self.input[member_start..self.position];
+            if let Some(previous) = previous {
+                if der_set_cmp(member, previous) == cmp::Ordering::Less {
+                    return Err(Error::SetNotSorted);
                 }
-                
-                self.structures.pop();
+            }
+            previous = Some(member);
+        }
+        
+        Ok(())
+    }
+    
+    /// The tag of the value most recently read by `next`. For a structure's end marker, this
+    /// is the tag of the structure. None if the last call failed before reading a tag.
+    pub fn last_tag(&self) -> Option<Tag> {
+        self.last_tag
+    }
+
+    /// Like `next`, but also returns the first identifier octet of the value's tag (which is
+    /// the whole tag, unless its number is 31 or more). An end marker comes with the tag of
+    /// the structure it ends, such as 0x30 for a SequenceEnd.
+    pub fn next_with_tag(&mut self) -> Result<(u8, Asn1Value<'a>), Error> {
+        let value = try!(self.next());
+        // next records the tag of every value it returns, so this never fails.
+        let tag = try!(self.last_tag.ok_or(Error::Malformed));
+        Ok((tag.first_octet(), value))
+    }
+
+    pub fn next(&mut self) -> Result<Asn1Value<'a>, Error> {
+        self.last_tag = None;
+        
+        if self.innermost_end <= self.position && !self.structures.is_empty() {
+            if self.innermost_end != self.position {
+                return Err(Error::StructureOverrun);
+            }
+            
+            if let Some(innermost_structure) = self.pop_structure() {
+                self.last_tag = Some(innermost_structure.tag);
                 return Ok(match innermost_structure.kind {
                     StructureKind::Sequence => Asn1Value::SequenceEnd,
                     StructureKind::Set => Asn1Value::SetEnd,
+                    StructureKind::Tagged(..) => Asn1Value::TaggedEnd,
+                    StructureKind::Unknown(..) => Asn1Value::UnknownEnd,
                 });
             }
         }
     
-        let value_type = try!(self.consume_one());
+        if self.values_read >= self.max_values && self.position < self.input.len() {
+            return Err(Error::TooManyValues);
+        }
+        let value = try!(self.read_value());
+        self.values_read += 1;
+        Ok(value)
+    }
+
+    /// Reads the value starting at the current position, for `next`.
+    fn read_value(&mut self) -> Result<Asn1Value<'a>, Error> {
+        let tag_start = self.position;
+        let tag = try!(self.read_tag());
+        self.last_tag = Some(tag);
         let length = try!(self.read_length());
+        try!(self.check_available(0));
+        
+        let tag_byte = self.input[tag_start];
+        if tag.class != TagClass::Universal {
+            if self.has_custom_handler(tag, tag_byte) {
+                return self.read_custom(length, tag_byte);
+            }
+            return self.read_tagged(length, tag);
+        }
         
-        match value_type {
+        match tag_byte {
             0x01 => self.read_boolean(length),
             0x02 => self.read_integer(length),
             0x03 => self.read_bit_string(length),
             0x04 => self.read_octet_string(length),
             0x05 => self.read_null(length),
             0x06 => self.read_object_identifier(length),
+            0x09 => self.read_real(length),
+            0x0A => self.read_enumerated(length),
             0x0C => self.read_utf8_string(length),
             0x13 => self.read_printable_string(length),
             0x16 => self.read_ia5_string(length),
+            0x17 => self.read_utc_time(length),
+            0x18 => self.read_generalized_time(length),
+            0x1B => self.read_general_string(length),
+            0x1C => self.read_universal_string(length),
             0x1E => self.read_bmp_string(length),
-            0x30 => self.read_sequence(length),
-            0x31 => self.read_set(length),
-            _ => Err(Error::UnrecognizedType)
+            0x23 | 0x24 => self.read_constructed_bit_or_octet_string(length, tag, tag_byte),
+            0x30 => self.read_sequence(length, tag),
+            0x31 => self.read_set(length, tag),
+            _ if self.has_custom_handler(tag, tag_byte) => self.read_custom(length, tag_byte),
+            _ => self.read_unknown(length, tag, tag_byte),
         }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::{Asn1Value, Parser};
-    use super::error::Error;
-    
-    #[test]
-    fn sequence() {
-        let bs = [0x30, 0x06,
-                  0x01, 0x01, 0x00,
-                  0x01, 0x01, 0xff];
-        let mut parser = Parser::new(&bs);
-        match parser.next().unwrap() {
-            Asn1Value::SequenceStart => {},
-            _ => { panic!("Expected sequence start"); }
-        };
-        
-        match parser.next().unwrap() {
-            Asn1Value::Boolean(false) => {},
-            _ => { panic!("Expected a 'false'"); }
-        };
-        
-        match parser.next().unwrap() {
-            Asn1Value::Boolean(true) => {},
-            _ => { panic!("Expected a 'true'"); }
-        };
-        
-        match parser.next().unwrap() {
-            Asn1Value::SequenceEnd => {},
-            _ => { panic!("Expected sequence end"); }
-        }
+    /// The tag of the innermost non-universal structure (such as an EXPLICIT `[0]`) that
+    /// the parser is currently inside, if any.
+    pub fn current_context_tag(&self) -> Option<(TagClass, u32)> {
+        self.structures.iter().rev().filter_map(|structure| {
+            match structure.kind {
+                StructureKind::Tagged(class, number) => Some((class, number)),
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// Reads an OCTET STRING and returns a new parser over its content, for the common case
+    /// of an OCTET STRING that holds further DER (as X.509 extension values do).
+    pub fn read_octet_string_as_parser(&mut self) -> Result<Parser<'a>, Error> {
+        let content = try!(self.next_octet_string());
+        Ok(Parser::new(content))
+    }
+
+    /// Returns a parser over whatever is left of the innermost structure, and moves this
+    /// parser to the structure's end. This suits an `ANY DEFINED BY` field, whose type is
+    /// only known after an earlier field has been read.
+    pub fn take_rest_of_structure(&mut self) -> Result<Parser<'a>, Error> {
+        let rest = self.remaining();
+        self.position += rest.len();
+        Ok(Parser::new(rest))
+    }
+
+    /// Reads the start of an EXPLICIT `[tag_number]` wrapper, leaving the parser at the
+    /// wrapped value. Its end marker (a TaggedEnd) follows the wrapped value. Each call peels
+    /// off exactly one layer, so `[0] EXPLICIT [1] EXPLICIT T` takes a call for each tag,
+    /// and is followed by two TaggedEnds.
+    pub fn read_explicit(&mut self, tag_number: u32) -> Result<(), Error> {
+        match try!(self.next_element()) {
+            Asn1Value::TaggedStart(TagClass::ContextSpecific, number) if number == tag_number => Ok(()),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Reads a `[tag_number] EXPLICIT OCTET STRING` whose content is further DER, returning a
+    /// parser over that content. The whole tagged value is consumed.
+    pub fn read_explicit_octet_string_inner(&mut self, tag_number: u32) -> Result<Parser<'a>, Error> {
+        try!(self.read_explicit(tag_number));
+        let inner = try!(self.read_octet_string_as_parser());
+        try!(self.expect_end());
+        Ok(inner)
+    }
+
+    /// Reads the end marker of the innermost structure, failing if it has values left.
+    fn expect_end(&mut self) -> Result<(), Error> {
+        match try!(self.next()) {
+            Asn1Value::SequenceEnd |
+            Asn1Value::SetEnd |
+            Asn1Value::TaggedEnd |
+            Asn1Value::UnknownEnd => Ok(()),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Reads a whole structure whose values are all primitive, returning those values. A
+    /// nested structure gives `UnexpectedType`.
+    pub fn collect_structure(&mut self) -> Result<Vec<Asn1Value<'a>>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::SequenceStart |
+            Asn1Value::SetStart |
+            Asn1Value::TaggedStart(..) |
+            Asn1Value::UnknownStart{..} => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        
+        let mut values = Vec::new();
+        while let Some(value) = try!(self.next_in_structure()) {
+            match value {
+                Asn1Value::SequenceStart |
+                Asn1Value::SetStart |
+                Asn1Value::TaggedStart(..) |
+                Asn1Value::UnknownStart{..} => { return Err(Error::UnexpectedType); }
+                value => { values.push(value); }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reads a SET of `SEQUENCE { OBJECT IDENTIFIER, value }` members, such as PKCS #9
+    /// attributes, returning each OID with the whole encoding of its value, in DER order.
+    pub fn collect_set_by_oid(&mut self) -> Result<Vec<(ObjectIdentifier<'a>, &'a [u8])>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::SetStart => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        
+        let mut members = Vec::new();
+        while let Some(member) = try!(self.next_in_structure()) {
+            match member {
+                Asn1Value::SequenceStart => {},
+                _ => { return Err(Error::UnexpectedType); }
+            }
+            let oid = try!(self.next_object_identifier());
+            let value = try!(self.read_any());
+            try!(self.expect_end());
+            members.push((oid, value));
+        }
+        Ok(members)
+    }
+
+    /// Calls `f` to read each of exactly `n` values from the innermost structure, then reads
+    /// the structure's end marker. Returns `WrongElementCount` if the structure ends before
+    /// `n` values have been read, or has values left over.
+    pub fn read_exact_elements(&mut self, n: usize, mut f: impl FnMut(&mut Parser<'a>) -> Result<(), Error>) -> Result<(), Error> {
+        for _ in 0..n {
+            match f(self) {
+                Err(Error::EndOfStructure) => { return Err(Error::WrongElementCount); }
+                result => { try!(result); }
+            }
+        }
+        
+        if !self.at_structure_end() {
+            return Err(Error::WrongElementCount);
+        }
+        self.expect_end()
+    }
+
+    /// Reads an OPTIONAL `[tag_number] EXPLICIT INTEGER`, such as the version field of an
+    /// X.509 certificate. If the next value isn't that tag, `default` is returned and nothing
+    /// is consumed.
+    pub fn read_optional_explicit_integer(&mut self, tag_number: u32, default: i64) -> Result<i64, Error> {
+        let expected = Tag{ class: TagClass::ContextSpecific, constructed: true, number: tag_number };
+        match self.peek_tag() {
+            Ok(tag) if tag == expected => {},
+            Ok(_) | Err(Error::EndOfStructure) | Err(Error::EOF) => { return Ok(default); }
+            Err(error) => { return Err(error); }
+        }
+        
+        try!(self.read_explicit(tag_number));
+        let value = try!(i64::try_from(&try!(self.next_integer())));
+        try!(self.expect_end());
+        Ok(value)
+    }
+
+    /// Reads an OPTIONAL `[tag_number] EXPLICIT` field, returning a parser over its content.
+    /// If the next value isn't that tag, `None` is returned and nothing is consumed, so a
+    /// run of OPTIONAL fields can be read by trying each tag in turn.
+    pub fn read_optional_by_tag(&mut self, tag_number: u32) -> Result<Option<Parser<'a>>, Error> {
+        let expected = Tag{ class: TagClass::ContextSpecific, constructed: true, number: tag_number };
+        match self.peek_tag() {
+            Ok(tag) if tag == expected => {},
+            Ok(_) | Err(Error::EndOfStructure) | Err(Error::EOF) => { return Ok(None); }
+            Err(error) => { return Err(error); }
+        }
+        
+        try!(self.read_explicit(tag_number));
+        let content = try!(self.take_rest_of_structure());
+        try!(self.expect_end());
+        Ok(Some(content))
+    }
+
+    /// Reads a `BOOLEAN DEFAULT default`. If the next value isn't a BOOLEAN, `default` is
+    /// returned and nothing is consumed.
+    pub fn read_optional_bool(&mut self, default: bool) -> Result<bool, Error> {
+        match self.peek_tag() {
+            Ok(Tag{ class: TagClass::Universal, constructed: false, number: 0x01 }) => {},
+            Ok(_) | Err(Error::EndOfStructure) | Err(Error::EOF) => { return Ok(default); }
+            Err(error) => { return Err(error); }
+        }
+        
+        let value = try!(self.next_boolean());
+        if value == default && self.reject_encoded_defaults {
+            return Err(Error::DefaultValueEncoded);
+        }
+        Ok(value)
+    }
+
+    /// Reads the next value in the innermost structure, or returns None (having consumed
+    /// the structure's end marker) if it has ended. A nested structure comes back as its
+    /// start marker, after which this reads that structure's values.
+    pub fn next_in_structure(&mut self) -> Result<Option<Asn1Value<'a>>, Error> {
+        match try!(self.next()) {
+            Asn1Value::SequenceEnd |
+            Asn1Value::SetEnd |
+            Asn1Value::TaggedEnd |
+            Asn1Value::UnknownEnd => Ok(None),
+            value => Ok(Some(value)),
+        }
+    }
+
+    /// Reads the next value, unless the innermost structure has ended, in which case
+    /// `EndOfStructure` is returned and nothing is consumed. The structure's end marker is
+    /// then still there to be read with `next`.
+    fn next_element(&mut self) -> Result<Asn1Value<'a>, Error> {
+        if self.at_structure_end() {
+            return Err(Error::EndOfStructure);
+        }
+        self.next()
+    }
+
+    // The typed accessors below read one value of a particular type. They return
+    // `UnexpectedType` if the value is of some other type, and `EndOfStructure` (without
+    // consuming anything) if the innermost structure has run out of values, so that a
+    // repeated field can be read with `while let Ok(x) = parser.next_integer()`.
+
+    pub fn next_boolean(&mut self) -> Result<bool, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::Boolean(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    pub fn next_integer(&mut self) -> Result<Integer<'a>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::Integer(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Reads an INTEGER whose content may be at most `max` bytes, for schemas that bound it.
+    pub fn next_integer_max_bytes(&mut self, max: usize) -> Result<Integer<'a>, Error> {
+        let x = try!(self.next_integer());
+        if x.as_bytes().len() > max {
+            return Err(Error::IntegerTooLong);
+        }
+        Ok(x)
+    }
+
+    pub fn next_enumerated(&mut self) -> Result<Integer<'a>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::Enumerated(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Reads an ENUMERATED and converts it to `T`, typically a Rust enum. Values that `T`
+    /// doesn't accept give `UnknownEnumerant`.
+    pub fn read_enumerated_as<T: TryFrom<i64>>(&mut self) -> Result<T, Error> {
+        let value = try!(self.next_enumerated());
+        value.as_i64().and_then(|x| T::try_from(x).ok()).ok_or(Error::UnknownEnumerant)
+    }
+
+    pub fn next_bit_string(&mut self) -> Result<BitString<'a>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::BitString(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    pub fn next_octet_string(&mut self) -> Result<&'a [u8], Error> {
+        match try!(self.next_element()) {
+            Asn1Value::OctetString(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    pub fn next_null(&mut self) -> Result<(), Error> {
+        match try!(self.next_element()) {
+            Asn1Value::Null => Ok(()),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    pub fn next_object_identifier(&mut self) -> Result<ObjectIdentifier<'a>, Error> {
+        match try!(self.next_element()) {
+            Asn1Value::ObjectIdentifier(x) => Ok(x),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    /// Reads an OCTET STRING, UTF8String, PrintableString or IA5String and returns its
+    /// content. BER allows these to be split into segments inside a constructed encoding;
+    /// when BER is allowed, those segments are put back together (and the result checked
+    /// against the string type's character set). DER only allows the primitive encoding, so
+    /// otherwise a constructed string gives `ConstructedMismatch`. Segments nested more than
+    /// `MAX_NESTING_DEPTH` deep give `NestingTooDeep`.
+    pub fn read_constructed_string(&mut self) -> Result<Vec<u8>, Error> {
+        let tag = try!(self.peek_tag());
+        if tag.class != TagClass::Universal {
+            return Err(Error::UnexpectedType);
+        }
+        match tag.number {
+            0x04 | 0x0C | 0x13 | 0x16 => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        if tag.constructed && !self.allow_ber {
+            return Err(Error::ConstructedMismatch);
+        }
+        
+        let mut content = Vec::new();
+        try!(self.read_string_segments(&mut content, 0));
+        
+        match tag.number {
+            0x0C => {
+                let utf8_str = try!(str::from_utf8(&content).map_err(|_| Error::InvalidUTF8));
+                try!(self.check_control_chars(utf8_str));
+            }
+            0x13 => { try!(to_printable_string(&content)); }
+            0x16 if !content.is_ascii() => { return Err(Error::InvalidIA5String); }
+            _ => {}
+        }
+        
+        Ok(content)
+    }
+
+    /// Appends the content of the string segment at the current position to `out`. `depth`
+    /// is how many constructed segments enclose it.
+    fn read_string_segments(&mut self, out: &mut Vec<u8>, depth: usize) -> Result<(), Error> {
+        let tag = try!(self.read_tag());
+        let length = try!(self.read_length());
+        if !tag.constructed {
+            out.extend_from_slice(try!(self.consume(length)));
+            return Ok(());
+        }
+        if depth == MAX_NESTING_DEPTH {
+            return Err(Error::NestingTooDeep);
+        }
+        
+        try!(self.check_available(length));
+        let end = self.position + length;
+        while self.position < end {
+            // Whatever the type of the whole string, its segments are OCTET STRINGs.
+            let segment_tag = try!(self.peek_tag());
+            if segment_tag.class != TagClass::Universal || segment_tag.number != 0x04 {
+                return Err(Error::Malformed);
+            }
+            
+            try!(self.read_string_segments(out, depth + 1));
+            if self.position > end {
+                return Err(Error::StructureOverrun);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `next`, but keeps going past values whose content is bad.
+    ///
+    /// When a value's header is intact but its content can't be decoded (an invalid string,
+    /// a malformed OID, an unrecognized type, ...), the error is recorded, the value is
+    /// skipped using its length, and parsing continues with the following value. Errors that
+    /// leave the position of the next value unknown (bad lengths, EOF, overruns) still stop
+    /// parsing; in that case the value is None and the fatal error is the last one returned.
+    pub fn next_lenient(&mut self) -> (Option<Asn1Value<'a>>, Vec<Error>) {
+        let mut errors = Vec::new();
+        loop {
+            let value_start = self.position;
+            let error = match self.next() {
+                Ok(value) => { return (Some(value), errors); }
+                Err(error) => error,
+            };
+            
+            errors.push(error);
+            if !error.is_recoverable() {
+                return (None, errors);
+            }
+            
+            self.position = value_start;
+            if let Err(error) = self.skip_value() {
+                errors.push(error);
+                return (None, errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Asn1Value, Parser, MAX_NESTING_DEPTH};
+    use super::error::Error;
+    use super::encoder::write_length;
+    use super::tag::{Tag, TagClass};
+    use std::convert::{TryFrom, TryInto};
+    
+    #[test]
+    fn sequence() {
+        let bs = [0x30, 0x06,
+                  0x01, 0x01, 0x00,
+                  0x01, 0x01, 0xff];
+        let mut parser = Parser::new(&bs);
+        match parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        };
+        
+        match parser.next().unwrap() {
+            Asn1Value::Boolean(false) => {},
+            _ => { panic!("Expected a 'false'"); }
+        };
+        
+        match parser.next().unwrap() {
+            Asn1Value::Boolean(true) => {},
+            _ => { panic!("Expected a 'true'"); }
+        };
+        
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
         
         match parser.next() {
             Err(Error::EOF) => {},
@@ -285,4 +1404,1276 @@ mod test {
         }
     }
 
+    #[test]
+    fn type_names() {
+        let date = ::time::DateTime{ year: 2024, month: 1, day: 1, hour: 0, minute: 0, second: 0, nanosecond: 0 };
+        let integer = ::integer::Integer::new(&[0x01]);
+        let values = [
+            (Asn1Value::Null, "NULL"),
+            (Asn1Value::Boolean(true), "BOOLEAN"),
+            (Asn1Value::Integer(integer), "INTEGER"),
+            (Asn1Value::Enumerated(integer), "ENUMERATED"),
+            (Asn1Value::Real(1.5), "REAL"),
+            (Asn1Value::BitString(::bit_string::BitString::new(&[0x00]).unwrap()), "BIT STRING"),
+            (Asn1Value::ObjectIdentifier(::object_identifier::ObjectIdentifier::new(&[0x2a]).unwrap()), "OBJECT IDENTIFIER"),
+            (Asn1Value::OctetString(&[]), "OCTET STRING"),
+            (Asn1Value::PrintableString(""), "PrintableString"),
+            (Asn1Value::Utf8String(""), "UTF8String"),
+            (Asn1Value::Ia5String(""), "IA5String"),
+            (Asn1Value::BmpString(String::new()), "BMPString"),
+            (Asn1Value::UniversalString(String::new()), "UniversalString"),
+            (Asn1Value::GeneralString(&[]), "GeneralString"),
+            (Asn1Value::UtcTime(date), "UTCTime"),
+            (Asn1Value::GeneralizedTime(date), "GeneralizedTime"),
+            (Asn1Value::SequenceStart, "SEQUENCE"),
+            (Asn1Value::SequenceEnd, "SEQUENCE"),
+            (Asn1Value::SetStart, "SET"),
+            (Asn1Value::SetEnd, "SET"),
+            (Asn1Value::TaggedStart(TagClass::ContextSpecific, 0), "tagged value"),
+            (Asn1Value::TaggedEnd, "tagged value"),
+            (Asn1Value::Tagged(TagClass::ContextSpecific, 0, &[]), "tagged value"),
+            (Asn1Value::Unknown{ tag: 0x07, content: &[] }, "unknown type"),
+            (Asn1Value::UnknownStart{ tag: 0x28 }, "unknown type"),
+            (Asn1Value::UnknownEnd, "unknown type"),
+            (Asn1Value::Custom{ tag: 0x80, value: Box::new(()) }, "custom value"),
+        ];
+        for &(ref value, name) in values.iter() {
+            assert_eq!(value.type_name(), name);
+        }
+    }
+
+    #[test]
+    fn values_with_tags() {
+        let bs = [0x30, 0x0b,
+                  0x01, 0x01, 0x00,
+                  0x31, 0x03, 0x02, 0x01, 0x07,
+                  0x9f, 0x20, 0x00];
+        let mut parser = Parser::new(&bs);
+        let mut tags = Vec::new();
+        while let Ok((tag, value)) = parser.next_with_tag() {
+            match (tag, value) {
+                (0x30, Asn1Value::SequenceStart) |
+                (0x01, Asn1Value::Boolean(false)) |
+                (0x31, Asn1Value::SetStart) |
+                (0x02, Asn1Value::Integer(_)) |
+                (0x31, Asn1Value::SetEnd) |
+                (0x9f, Asn1Value::Tagged(TagClass::ContextSpecific, 32, _)) |
+                (0x30, Asn1Value::SequenceEnd) => {},
+                (tag, _) => { panic!("Unexpected value with tag {:#04x}", tag); }
+            }
+            tags.push(tag);
+        }
+        assert_eq!(tags, [0x30, 0x01, 0x31, 0x02, 0x31, 0x9f, 0x30]);
+        assert_eq!(parser.next_with_tag().err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn set_of_order() {
+        let sorted = [0x31, 0x06,
+                      0x02, 0x01, 0x01,
+                      0x02, 0x01, 0x02];
+        let mut parser = Parser::new(&sorted);
+        parser.expect_set_of().unwrap();
+        match parser.next().unwrap() {
+            Asn1Value::Integer(x) => { assert_eq!(x.as_u8(), Some(1)); },
+            _ => { panic!("Expected an integer"); }
+        }
+        
+        let unsorted = [0x31, 0x06,
+                        0x02, 0x01, 0x02,
+                        0x02, 0x01, 0x01];
+        let mut parser = Parser::new(&unsorted);
+        match parser.expect_set_of() {
+            Err(Error::SetNotSorted) => {},
+            _ => { panic!("Expected SetNotSorted"); }
+        }
+    }
+
+    #[test]
+    fn set_order() {
+        let sorted = [0x31, 0x09,
+                      0x01, 0x01, 0xff,
+                      0x02, 0x01, 0x05,
+                      0x80, 0x01, 0x00];
+        let mut parser = Parser::new(&sorted);
+        parser.expect_set().unwrap();
+        assert_eq!(parser.next_boolean(), Ok(true));
+        
+        // An INTEGER (tag 2) before a BOOLEAN (tag 1).
+        let unsorted = [0x31, 0x06,
+                        0x02, 0x01, 0x05,
+                        0x01, 0x01, 0xff];
+        assert_eq!(Parser::new(&unsorted).expect_set(), Err(Error::SetNotSorted));
+        
+        // Context-specific tags come after universal ones, regardless of number.
+        let classes = [0x31, 0x06,
+                       0x80, 0x01, 0x00,
+                       0x02, 0x01, 0x05];
+        assert_eq!(Parser::new(&classes).expect_set(), Err(Error::SetNotSorted));
+        
+        // Two members with the same tag can't both belong to a SET.
+        let repeated = [0x31, 0x06,
+                        0x02, 0x01, 0x01,
+                        0x02, 0x01, 0x02];
+        assert_eq!(Parser::new(&repeated).expect_set(), Err(Error::SetNotSorted));
+    }
+
+    #[test]
+    fn lenient() {
+        let bs = [0x30, 0x0b,
+                  0x13, 0x01, b'@',
+                  0x0c, 0x01, 0xff,
+                  0x01, 0x01, 0xff,
+                  0x04, 0x05];
+        let mut parser = Parser::new(&bs);
+        match parser.next_lenient() {
+            (Some(Asn1Value::SequenceStart), ref errors) if errors.is_empty() => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        
+        let (value, errors) = parser.next_lenient();
+        match value {
+            Some(Asn1Value::Boolean(true)) => {},
+            _ => { panic!("Expected a 'true'"); }
+        }
+        match &errors[..] {
+            [Error::InvalidPrintableString, Error::InvalidUTF8] => {},
+            _ => { panic!("Expected both string errors"); }
+        }
+        
+        let (value, errors) = parser.next_lenient();
+        assert!(value.is_none());
+        match &errors[..] {
+            [Error::StructureOverrun] => {},
+            _ => { panic!("Expected StructureOverrun"); }
+        }
+    }
+
+    #[test]
+    fn context_tags() {
+        let bs = [0x30, 0x0b,
+                  0xa1, 0x03,
+                  0x02, 0x01, 0x05,
+                  0x81, 0x01, 0x07,
+                  0x01, 0x01, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.current_context_tag(), None);
+        
+        match parser.next().unwrap() {
+            Asn1Value::TaggedStart(TagClass::ContextSpecific, 1) => {},
+            _ => { panic!("Expected [1] start"); }
+        }
+        assert_eq!(parser.current_context_tag(), Some((TagClass::ContextSpecific, 1)));
+        
+        match parser.next().unwrap() {
+            Asn1Value::Integer(x) => { assert_eq!(x.as_u8(), Some(5)); },
+            _ => { panic!("Expected an integer"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::TaggedEnd => {},
+            _ => { panic!("Expected [1] end"); }
+        }
+        assert_eq!(parser.current_context_tag(), None);
+        
+        match parser.next().unwrap() {
+            Asn1Value::Tagged(TagClass::ContextSpecific, 1, content) => { assert_eq!(content, [0x07]); },
+            _ => { panic!("Expected a primitive [1]"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::Boolean(false) => {},
+            _ => { panic!("Expected a 'false'"); }
+        }
+    }
+
+    #[test]
+    fn try_into_values() {
+        fn parse_all(bs: &[u8]) -> Result<(bool, &str, &[u8]), Error> {
+            let mut parser = Parser::new(bs);
+            let b: bool = try!(try!(parser.next()).try_into());
+            let s: &str = try!(try!(parser.next()).try_into());
+            let o: &[u8] = try!(try!(parser.next()).try_into());
+            Ok((b, s, o))
+        }
+        
+        let bs = [0x01, 0x01, 0xff,
+                  0x13, 0x02, b'h', b'i',
+                  0x04, 0x01, 0x2a];
+        let (b, s, o) = parse_all(&bs).unwrap();
+        assert!(b);
+        assert_eq!(s, "hi");
+        assert_eq!(o, [0x2a]);
+        
+        let mismatched = [0x05, 0x00];
+        match parse_all(&mismatched) {
+            Err(Error::UnexpectedType) => {},
+            _ => { panic!("Expected UnexpectedType"); }
+        }
+    }
+
+    #[test]
+    fn peek() {
+        let bs = [0x30, 0x81, 0x80];
+        let parser = Parser::new(&bs);
+        assert_eq!(parser.peek_tag().unwrap().number, 16);
+        assert_eq!(parser.peek_length().unwrap(), (3, 128));
+        assert_eq!(parser.peek_length().unwrap(), (3, 128));
+        
+        let bs = [0x30, 0x03,
+                  0x02, 0x01, 0x07];
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.peek_constructed(), Ok(true));
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.peek_constructed(), Ok(false));
+        assert_eq!(parser.peek_length().unwrap(), (2, 1));
+        match parser.next().unwrap() {
+            Asn1Value::Integer(x) => { assert_eq!(x.as_u8(), Some(7)); },
+            _ => { panic!("Expected an integer"); }
+        }
+        
+        // Peeking does not look past the end of the sequence.
+        assert!(parser.peek_length().is_err());
+        assert_eq!(parser.peek_constructed(), Err(Error::EndOfStructure));
+        
+        let truncated = [0x04, 0x82, 0x01];
+        let parser = Parser::new(&truncated);
+        match parser.peek_length() {
+            Err(Error::EOF) => {},
+            _ => { panic!("Expected EOF"); }
+        }
+    }
+
+    #[test]
+    fn unknown_types() {
+        let bs = [0x0e, 0x01, 0xaa,
+                  0x28, 0x02,
+                  0x05, 0x00];
+        
+        let mut strict = Parser::new(&bs);
+        match strict.next() {
+            Err(Error::UnrecognizedType) => {},
+            _ => { panic!("Expected UnrecognizedType"); }
+        }
+        
+        let mut parser = Parser::new(&bs);
+        parser.set_tolerate_unknown(true);
+        match parser.next().unwrap() {
+            Asn1Value::Unknown{ tag: 0x0e, content } => { assert_eq!(content, [0xaa]); },
+            _ => { panic!("Expected an unknown primitive"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::UnknownStart{ tag: 0x28 } => {},
+            _ => { panic!("Expected an unknown structure start"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::Null => {},
+            _ => { panic!("Expected a null"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::UnknownEnd => {},
+            _ => { panic!("Expected an unknown structure end"); }
+        }
+    }
+
+    #[test]
+    fn lenient_length() {
+        let bs = [0x30, 0x81, 0x05, 0x04, 0x82, 0x00, 0x01, 0xaa];
+        assert_eq!(Parser::new(&bs).next().err(), Some(Error::InvalidLengthEncoding));
+        
+        let mut parser = Parser::new(&bs);
+        parser.set_strict_length(false);
+        assert_eq!(parser.peek_length(), Ok((3, 5)));
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next_octet_string(), Ok(&[0xaa][..]));
+        assert_eq!(parser.length_warnings(), [1, 4]);
+        
+        let mut parser = Parser::new(&[0x04, 0x80, 0x00, 0x00]);
+        parser.set_strict_length(false);
+        assert_eq!(parser.next().err(), Some(Error::InvalidLengthEncoding));
+    }
+
+    #[test]
+    fn zero_padded_length() {
+        // Twice as many length bytes as a usize has, most of them leading zeros.
+        let mut bs = vec![0x04, 0x80 | (2 * ::usize_bytes()) as u8];
+        bs.extend(vec![0x00; 2 * ::usize_bytes() - 1]);
+        bs.extend_from_slice(&[0x02, 0xaa, 0xbb]);
+        assert_eq!(Parser::new(&bs).next().err(), Some(Error::OverlongLength));
+        
+        let mut parser = Parser::new(&bs);
+        parser.set_strict_length(false);
+        assert_eq!(parser.next_octet_string(), Ok(&[0xaa, 0xbb][..]));
+        assert_eq!(parser.length_warnings(), [1]);
+        
+        // Padding doesn't help a value that doesn't fit in a usize at all.
+        let mut bs = vec![0x04, 0x80 | (2 * ::usize_bytes()) as u8, 0x00, 0x01];
+        bs.extend(vec![0x00; 2 * ::usize_bytes() - 2]);
+        let mut parser = Parser::new(&bs);
+        parser.set_strict_length(false);
+        assert_eq!(parser.next().err(), Some(Error::OverlongLength));
+    }
+
+    #[test]
+    fn reserved_length() {
+        let mut bs = vec![0x04, 0xff];
+        bs.extend_from_slice(&[0x00; 127]);
+        assert_eq!(Parser::new(&bs).next().err(), Some(Error::ReservedLength));
+        assert_eq!(Parser::new(&bs[..2]).next().err(), Some(Error::ReservedLength));
+        assert_eq!(Parser::new(&[0x04, 0xfe]).next().err(), Some(Error::OverlongLength));
+    }
+
+    #[test]
+    fn maximum_length() {
+        // Lengths that would put the end of a value at (or just short of) usize::MAX.
+        for &length in [usize::MAX, usize::MAX - 1, usize::MAX - 12].iter() {
+            let length_bytes = length.to_be_bytes();
+            let mut structure = vec![0x30, 0x80 | length_bytes.len() as u8];
+            structure.extend_from_slice(&length_bytes);
+            structure.extend_from_slice(&[0x05, 0x00]);
+            
+            let parser = Parser::new(&structure);
+            assert_eq!(parser.peek_length(), Ok((2 + length_bytes.len(), length)));
+            assert_eq!(Parser::new(&structure).next().err(), Some(Error::EOF));
+            assert_eq!(Parser::new(&structure).read_any(), Err(Error::EOF));
+            assert_eq!(Parser::new_framed(&structure).err(), Some(Error::EOF));
+            assert!(::x509::open_certificate(&structure).is_err());
+            assert!(::tlv::parse_tlv_tree(&structure).is_err());
+            assert!(::encoding::encoding_kind(&structure).is_err());
+            
+            let mut nested = vec![0x30, structure.len() as u8];
+            nested.extend_from_slice(&structure);
+            let mut parser = Parser::new(&nested);
+            assert!(parser.next().is_ok());
+            assert_eq!(parser.next().err(), Some(Error::StructureOverrun));
+            
+            // The same, as segments of a constructed OCTET STRING.
+            nested[0] = 0x24;
+            nested[2] = 0x24;
+            let mut parser = Parser::new(&nested);
+            parser.set_allow_ber(true);
+            assert_eq!(parser.read_constructed_string().err(), Some(Error::EOF));
+        }
+    }
+
+    #[test]
+    fn value_overruns_structure() {
+        // The OCTET STRING claims 5 bytes, which exist in the input, but only 2 of them are
+        // inside the SEQUENCE.
+        let bs = [0x30, 0x04,
+                  0x04, 0x05, 0xaa, 0xbb,
+                  0xcc, 0xdd, 0xee];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next().err(), Some(Error::StructureOverrun));
+        
+        let strings = [0x30, 0x03, 0x0c, 0x02, b'a', b'b',
+                       0x30, 0x03, 0x13, 0x02, b'a', b'b',
+                       0x30, 0x02, 0x01, 0x01, 0xff,
+                       0x30, 0x03, 0x30, 0x02, 0x05, 0x00];
+        for chunk in [&strings[0..6], &strings[6..12], &strings[12..17], &strings[17..]].iter() {
+            let mut parser = Parser::new(chunk);
+            assert!(parser.next().is_ok());
+            assert_eq!(parser.next().err(), Some(Error::StructureOverrun));
+        }
+        
+        // The header itself running past the end leaves bytes that can't be a value.
+        let header = [0x30, 0x02, 0x04, 0x81, 0x80];
+        let mut parser = Parser::new(&header);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next().err(), Some(Error::IncompleteStructureContent));
+    }
+
+    #[test]
+    fn incomplete_structure_content() {
+        // A lone byte left at the end of the SEQUENCE, after a complete NULL.
+        let bs = [0x30, 0x03, 0x05, 0x00, 0x02, 0x01, 0x07];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next().err(), Some(Error::IncompleteStructureContent));
+        
+        // A multi-byte tag cut short by the end of the structure.
+        let bs = [0x30, 0x02, 0x9f, 0x81, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.next().err(), Some(Error::IncompleteStructureContent));
+        
+        // At the top level, the same thing is just the end of the input.
+        assert_eq!(Parser::new(&[0x02]).next().err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let samples: &[&[u8]] = &[
+            &[0x05, 0x00],
+            &[0x01, 0x01, 0x00],
+            &[0x01, 0x01, 0xff],
+            &[0x02, 0x01, 0x80],
+            &[0x02, 0x03, 0x01, 0x00, 0x01],
+            &[0x06, 0x03, 0x2a, 0x86, 0x48],
+            &[0x03, 0x01, 0x00],
+            &[0x03, 0x03, 0x06, 0x6e, 0x40],
+            &[0x04, 0x00],
+            &[0x04, 0x02, 0xde, 0xad],
+            &[0x13, 0x02, b'h', b'i'],
+            &[0x0c, 0x02, 0xc3, 0xa9],
+            &[0x82, 0x01, 0x07],
+            &[0x9f, 0x81, 0x00, 0x01, 0x07],
+        ];
+        
+        for sample in samples.iter() {
+            let mut parser = Parser::new(sample);
+            let value = parser.next().unwrap();
+            let mut out = Vec::new();
+            value.encode(&mut out).unwrap();
+            assert_eq!(&out[..], *sample);
+        }
+        
+        let mut out = Vec::new();
+        assert_eq!(Asn1Value::SequenceStart.encode(&mut out), Err(Error::UnencodableValue));
+        assert_eq!(Asn1Value::SetEnd.encode(&mut out), Err(Error::UnencodableValue));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn limit() {
+        let mut buffer = vec![0u8; 64];
+        buffer[0..4].copy_from_slice(&[0x04, 0x20, 0xaa, 0xbb]);
+        
+        let mut unlimited = Parser::new(&buffer);
+        assert!(unlimited.next().is_ok());
+        
+        let mut limited = Parser::with_limit(&buffer, 16);
+        assert_eq!(limited.next().err(), Some(Error::EOF));
+        
+        let mut roomy = Parser::with_limit(&buffer[..34], 1000);
+        assert!(roomy.next().is_ok());
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let bs = [0x30, 0x09,
+                  0x02, 0x01, 0x01,
+                  0x02, 0x01, 0x02,
+                  0x02, 0x01, 0x03,
+                  0x01, 0x01, 0xff];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        
+        let mut values = Vec::new();
+        while let Ok(x) = parser.next_integer() {
+            values.push(x.as_u8().unwrap());
+        }
+        assert_eq!(values, [1, 2, 3]);
+        
+        assert_eq!(parser.next_integer().err(), Some(Error::EndOfStructure));
+        assert_eq!(parser.peek_tag().err(), Some(Error::EndOfStructure));
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        
+        assert_eq!(parser.next_integer().err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn integer_max_bytes() {
+        let bs = [0x02, 0x08, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                  0x02, 0x09, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.next_integer_max_bytes(8).unwrap().as_i64(), Some(i64::MAX));
+        assert_eq!(parser.next_integer_max_bytes(8).err(), Some(Error::IntegerTooLong));
+        
+        assert_eq!(Parser::new(&bs[10..]).next_integer_max_bytes(9).unwrap().as_u64(), Some(1 << 63));
+    }
+
+    #[test]
+    fn constructed_strings() {
+        let segmented = [0x24, 0x08,
+                         0x04, 0x02, 0xaa, 0xbb,
+                         0x04, 0x02, 0xcc, 0xdd];
+        
+        let mut der = Parser::new(&segmented);
+        assert_eq!(der.read_constructed_string().err(), Some(Error::ConstructedMismatch));
+        
+        let mut ber = Parser::new(&segmented);
+        ber.set_allow_ber(true);
+        assert_eq!(ber.read_constructed_string().unwrap(), [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(ber.next().err(), Some(Error::EOF));
+        
+        // Segments can themselves be constructed.
+        let nested = [0x36, 0x0b,
+                      0x04, 0x01, b'a',
+                      0x24, 0x06,
+                      0x04, 0x01, b'b',
+                      0x04, 0x01, b'c'];
+        let mut ber = Parser::new(&nested);
+        ber.set_allow_ber(true);
+        assert_eq!(ber.read_constructed_string().unwrap(), b"abc");
+        
+        let bad_charset = [0x33, 0x06,
+                           0x04, 0x01, b'a',
+                           0x04, 0x01, b'@'];
+        let mut ber = Parser::new(&bad_charset);
+        ber.set_allow_ber(true);
+        assert_eq!(ber.read_constructed_string().err(), Some(Error::InvalidPrintableString));
+        
+        let primitive = [0x0c, 0x02, b'h', b'i'];
+        let mut der = Parser::new(&primitive);
+        assert_eq!(der.read_constructed_string().unwrap(), b"hi");
+    }
+
+    #[test]
+    fn deeply_nested_string_segments() {
+        // `depth` constructed OCTET STRINGs, each the only segment of the last, around "x".
+        fn nested(depth: usize) -> Vec<u8> {
+            let mut lengths = Vec::new();
+            let mut size = 3;
+            for _ in 0..depth {
+                lengths.push(size);
+                let mut header = vec![0x24];
+                write_length(&mut header, size);
+                size += header.len();
+            }
+            
+            let mut bs = Vec::new();
+            for length in lengths.iter().rev() {
+                bs.push(0x24);
+                write_length(&mut bs, *length);
+            }
+            bs.extend_from_slice(&[0x04, 0x01, b'x']);
+            bs
+        }
+        
+        let read = |bs: &[u8]| {
+            let mut parser = Parser::new(bs);
+            parser.set_allow_ber(true);
+            parser.read_constructed_string()
+        };
+        assert_eq!(read(&nested(MAX_NESTING_DEPTH)), Ok(b"x".to_vec()));
+        assert_eq!(read(&nested(MAX_NESTING_DEPTH + 1)), Err(Error::NestingTooDeep));
+        assert_eq!(read(&nested(200_000)), Err(Error::NestingTooDeep));
+    }
+
+    #[test]
+    fn constructed_octet_string_in_next() {
+        let segmented = [0x24, 0x08,
+                         0x04, 0x02, 0xaa, 0xbb,
+                         0x04, 0x02, 0xcc, 0xdd];
+        
+        let mut der = Parser::new(&segmented);
+        der.set_tolerate_unknown(true);
+        assert_eq!(der.next().err(), Some(Error::ConstructedMismatch));
+        assert_eq!(Parser::new(&[0x23, 0x00]).next().err(), Some(Error::ConstructedMismatch));
+        
+        let mut ber = Parser::new(&segmented);
+        ber.set_allow_ber(true);
+        match ber.next().unwrap() {
+            Asn1Value::UnknownStart{ tag: 0x24 } => {},
+            _ => { panic!("Expected a constructed OCTET STRING"); }
+        }
+        assert_eq!(ber.next_octet_string(), Ok(&[0xaa, 0xbb][..]));
+        assert_eq!(ber.next_octet_string(), Ok(&[0xcc, 0xdd][..]));
+        match ber.next().unwrap() {
+            Asn1Value::UnknownEnd => {},
+            _ => { panic!("Expected the end of the OCTET STRING"); }
+        }
+    }
+
+    #[test]
+    fn collect_structure() {
+        let bs = [0x30, 0x09,
+                  0x01, 0x01, 0xff,
+                  0x01, 0x01, 0x00,
+                  0x01, 0x01, 0xff,
+                  0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        let values = parser.collect_structure().unwrap();
+        assert_eq!(values.len(), 3);
+        let booleans: Vec<bool> = values.into_iter().map(|x| x.try_into().unwrap()).collect();
+        assert_eq!(booleans, [true, false, true]);
+        assert_eq!(parser.next_null(), Ok(()));
+        
+        let nested = [0x30, 0x02, 0x30, 0x00];
+        assert_eq!(Parser::new(&nested).collect_structure().err(), Some(Error::UnexpectedType));
+        assert_eq!(Parser::new(&bs[2..]).collect_structure().err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn set_by_oid() {
+        // contentType and signingTime attributes, each with a SET OF one value.
+        let bs = [0x31, 0x2a,
+                  0x30, 0x12,
+                  0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03,
+                  0x31, 0x05, 0x06, 0x03, 0x2a, 0x03, 0x04,
+                  0x30, 0x14,
+                  0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x05,
+                  0x31, 0x07, 0x05, 0x00, 0x05, 0x00, 0x05, 0x00, 0x05, 0x00];
+        let members = Parser::new(&bs).collect_set_by_oid().unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].0.as_bytes(), &bs[6..15]);
+        assert_eq!(members[0].1, &bs[15..22]);
+        assert_eq!(members[1].0.as_bytes(), &bs[26..35]);
+        assert_eq!(members[1].1, &bs[35..44]);
+        
+        let not_a_pair = [0x31, 0x09, 0x30, 0x07, 0x06, 0x01, 0x2a, 0x05, 0x00, 0x05, 0x00];
+        assert_eq!(Parser::new(&not_a_pair).collect_set_by_oid().err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn exact_elements() {
+        let bs = [0x30, 0x09,
+                  0x02, 0x01, 0x01,
+                  0x02, 0x01, 0x02,
+                  0x02, 0x01, 0x03];
+        let cases = [(3, Ok(()), &[1, 2, 3][..]),
+                     (2, Err(Error::WrongElementCount), &[1, 2][..]),
+                     (4, Err(Error::WrongElementCount), &[1, 2, 3][..])];
+        for &(n, expected, expected_values) in cases.iter() {
+            let mut parser = Parser::new(&bs);
+            assert!(parser.next().is_ok());
+            
+            let mut values = Vec::new();
+            let result = parser.read_exact_elements(n, |parser| {
+                values.push(try!(parser.next_integer()).as_u8().unwrap());
+                Ok(())
+            });
+            assert_eq!(result, expected);
+            assert_eq!(values, expected_values);
+        }
+    }
+
+    #[test]
+    fn values_in_structure() {
+        let bs = [0x30, 0x0b,
+                  0x30, 0x06,
+                  0x01, 0x01, 0x00,
+                  0x01, 0x01, 0xff,
+                  0x02, 0x01, 0x09];
+        let mut parser = Parser::new(&bs);
+        match parser.next_in_structure().unwrap() {
+            Some(Asn1Value::SequenceStart) => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        match parser.next_in_structure().unwrap() {
+            Some(Asn1Value::SequenceStart) => {},
+            _ => { panic!("Expected inner sequence start"); }
+        }
+        
+        let mut inner = Vec::new();
+        while let Some(value) = parser.next_in_structure().unwrap() {
+            match value {
+                Asn1Value::Boolean(x) => { inner.push(x); },
+                _ => { panic!("Expected a boolean"); }
+            }
+        }
+        assert_eq!(inner, [false, true]);
+        
+        match parser.next_in_structure().unwrap() {
+            Some(Asn1Value::Integer(x)) => { assert_eq!(x.as_u8(), Some(9)); },
+            _ => { panic!("Expected an integer"); }
+        }
+        assert!(parser.next_in_structure().unwrap().is_none());
+        assert_eq!(parser.next_in_structure().err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn nested_explicit() {
+        let bs = [0xa0, 0x05, 0xa1, 0x03, 0x02, 0x01, 0x2a];
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.read_explicit(0), Ok(()));
+        assert_eq!(parser.current_context_tag(), Some((TagClass::ContextSpecific, 0)));
+        assert_eq!(parser.read_explicit(1), Ok(()));
+        assert_eq!(parser.current_context_tag(), Some((TagClass::ContextSpecific, 1)));
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(42));
+        for _ in 0..2 {
+            match parser.next().unwrap() {
+                Asn1Value::TaggedEnd => {},
+                _ => { panic!("Expected the end of an EXPLICIT tag"); }
+            }
+        }
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.read_explicit(1), Err(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn explicit_octet_string_inner() {
+        let bs = [0xa0, 0x09,
+                  0x04, 0x07,
+                  0x30, 0x05, 0x02, 0x01, 0x03, 0x05, 0x00,
+                  0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        let mut inner = parser.read_explicit_octet_string_inner(0).unwrap();
+        match inner.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        assert_eq!(inner.next_integer().unwrap().as_u8(), Some(3));
+        match parser.next().unwrap() {
+            Asn1Value::Null => {},
+            _ => { panic!("Expected the value after the tagged one"); }
+        }
+        
+        assert_eq!(Parser::new(&bs).read_explicit_octet_string_inner(1).err(), Some(Error::UnexpectedType));
+        let extra = [0xa0, 0x04, 0x04, 0x00, 0x05, 0x00];
+        assert_eq!(Parser::new(&extra).read_explicit_octet_string_inner(0).err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn optional_by_tag() {
+        // [1] and [2] are OPTIONAL, but only [2] is present, followed by another field.
+        let bs = [0x30, 0x07,
+                  0xa2, 0x03, 0x02, 0x01, 0x09,
+                  0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert!(parser.read_optional_by_tag(1).unwrap().is_none());
+        let mut second = parser.read_optional_by_tag(2).unwrap().unwrap();
+        assert_eq!(second.next_integer().unwrap().as_u8(), Some(9));
+        assert_eq!(second.next().err(), Some(Error::EOF));
+        
+        assert!(parser.read_optional_by_tag(2).unwrap().is_none());
+        match parser.next().unwrap() {
+            Asn1Value::Null => {},
+            _ => { panic!("Expected the field after the optional ones"); }
+        }
+        assert!(parser.read_optional_by_tag(1).unwrap().is_none());
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        assert!(parser.read_optional_by_tag(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn optional_explicit_integer() {
+        let present = [0x30, 0x08,
+                       0xa0, 0x03, 0x02, 0x01, 0x02,
+                       0x02, 0x01, 0x05];
+        let mut parser = Parser::new(&present);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.read_optional_explicit_integer(0, 0), Ok(2));
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(5));
+        
+        let absent = [0x30, 0x03,
+                      0x02, 0x01, 0x05];
+        let mut parser = Parser::new(&absent);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.read_optional_explicit_integer(0, 0), Ok(0));
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(5));
+        assert_eq!(parser.read_optional_explicit_integer(0, 7), Ok(7));
+        
+        let extra = [0xa0, 0x05, 0x02, 0x01, 0x02, 0x05, 0x00];
+        let mut parser = Parser::new(&extra);
+        assert_eq!(parser.read_optional_explicit_integer(0, 0), Err(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn optional_bool() {
+        let bs = [0x30, 0x06, 0x01, 0x01, 0xff, 0x02, 0x01, 0x05];
+        let mut parser = Parser::new(&bs);
+        parser.set_reject_encoded_defaults(true);
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.read_optional_bool(false), Ok(true));
+        assert_eq!(parser.read_optional_bool(false), Ok(false));
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(5));
+        assert_eq!(parser.read_optional_bool(true), Ok(true));
+        
+        let mut parser = Parser::new(&bs[2..5]);
+        parser.set_reject_encoded_defaults(true);
+        assert_eq!(parser.read_optional_bool(true), Err(Error::DefaultValueEncoded));
+        
+        let mut parser = Parser::new(&bs[2..5]);
+        assert_eq!(parser.read_optional_bool(true), Ok(true));
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum ReasonCode {
+        Unspecified,
+        KeyCompromise,
+        CaCompromise,
+        RemoveFromCrl,
+    }
+
+    impl TryFrom<i64> for ReasonCode {
+        type Error = ();
+        fn try_from(x: i64) -> Result<ReasonCode, ()> {
+            match x {
+                0 => Ok(ReasonCode::Unspecified),
+                1 => Ok(ReasonCode::KeyCompromise),
+                2 => Ok(ReasonCode::CaCompromise),
+                8 => Ok(ReasonCode::RemoveFromCrl),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn enumerated() {
+        let bs = [0x0a, 0x01, 0x01, 0x0a, 0x01, 0x08, 0x0a, 0x01, 0x07, 0x02, 0x01, 0x01];
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.read_enumerated_as::<ReasonCode>(), Ok(ReasonCode::KeyCompromise));
+        assert_eq!(parser.read_enumerated_as::<ReasonCode>(), Ok(ReasonCode::RemoveFromCrl));
+        assert_eq!(parser.read_enumerated_as::<ReasonCode>(), Err(Error::UnknownEnumerant));
+        assert_eq!(parser.read_enumerated_as::<ReasonCode>(), Err(Error::UnexpectedType));
+        
+        let mut out = Vec::new();
+        Parser::new(&bs).next().unwrap().encode(&mut out).unwrap();
+        assert_eq!(&out[..], &bs[..3]);
+    }
+
+    #[test]
+    fn ia5_and_bmp_strings() {
+        let bs = [0x16, 0x05, b'a', b'@', b'b', b'.', b'c',
+                  0x1e, 0x06, 0x00, b'h', 0x00, b'i', 0x20, 0xac];
+        let mut parser = Parser::new(&bs);
+        match parser.next().unwrap() {
+            Asn1Value::Ia5String(x) => { assert_eq!(x, "a@b.c"); },
+            _ => { panic!("Expected an IA5String"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::BmpString(x) => { assert_eq!(x, "hi\u{20ac}"); },
+            _ => { panic!("Expected a BMPString"); }
+        }
+        
+        for sample in [&bs[..7], &bs[7..]].iter() {
+            let mut out = Vec::new();
+            Parser::new(sample).next().unwrap().encode(&mut out).unwrap();
+            assert_eq!(&out[..], *sample);
+        }
+        
+        assert_eq!(Parser::new(&[0x16, 0x01, 0xe9]).next().err(), Some(Error::InvalidIA5String));
+        assert_eq!(Parser::new(&[0x1e, 0x03, 0x00, b'h', 0x00]).next().err(), Some(Error::InvalidBMPString));
+        assert_eq!(Parser::new(&[0x1e, 0x02, 0xd8, 0x00]).next().err(), Some(Error::InvalidBMPString));
+        
+        let mut out = Vec::new();
+        assert_eq!(Asn1Value::BmpString("\u{1f600}".to_string()).encode(&mut out), Err(Error::UnencodableValue));
+    }
+
+    #[test]
+    fn real() {
+        let bs = [0x30, 0x0a,
+                  0x09, 0x00,
+                  0x09, 0x01, 0x41,
+                  0x09, 0x03, 0x80, 0xff, 0x03];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        let mut reals = Vec::new();
+        while let Some(value) = parser.next_in_structure().unwrap() {
+            match value {
+                Asn1Value::Real(x) => { reals.push(x); },
+                _ => { panic!("Expected a REAL"); }
+            }
+        }
+        assert_eq!(reals, [0.0, f64::NEG_INFINITY, 1.5]);
+        
+        for sample in [&bs[2..4], &bs[4..7], &bs[7..]].iter() {
+            let mut out = Vec::new();
+            Parser::new(sample).next().unwrap().encode(&mut out).unwrap();
+            assert_eq!(&out[..], *sample);
+        }
+        
+        // Negative zero is BER-only.
+        let minus_zero = [0x09, 0x01, 0x43];
+        assert_eq!(Parser::new(&minus_zero).next().err(), Some(Error::InvalidReal));
+        let mut parser = Parser::new(&minus_zero);
+        parser.set_allow_ber(true);
+        match parser.next().unwrap() {
+            Asn1Value::Real(x) => { assert!(x == 0.0 && x.is_sign_negative()); },
+            _ => { panic!("Expected a REAL"); }
+        }
+    }
+
+    #[test]
+    fn universal_string() {
+        let bs = [0x1c, 0x08, 0x00, 0x00, 0x00, b'a', 0x00, 0x01, 0xf6, 0x00];
+        match Parser::new(&bs).next().unwrap() {
+            Asn1Value::UniversalString(x) => { assert_eq!(x, "a\u{1f600}"); },
+            _ => { panic!("Expected a UniversalString"); }
+        }
+        let mut out = Vec::new();
+        Parser::new(&bs).next().unwrap().encode(&mut out).unwrap();
+        assert_eq!(&out[..], &bs[..]);
+        
+        assert_eq!(Parser::new(&[0x1c, 0x04, 0x00, 0x11, 0x00, 0x00]).next().err(), Some(Error::InvalidUniversalString));
+    }
+
+    #[test]
+    fn string_unit_lengths() {
+        // The content lengths are rejected up front, before even checking the content is there.
+        let bmp = [0x1e, 0x03, 0x00, b'h', 0x00];
+        assert_eq!(Parser::new(&bmp).next().err(), Some(Error::InvalidBMPString));
+        assert_eq!(Parser::new(&bmp[..3]).next().err(), Some(Error::InvalidBMPString));
+        
+        let universal = [0x1c, 0x05, 0x00, 0x00, 0x00, b'a', 0x00];
+        assert_eq!(Parser::new(&universal).next().err(), Some(Error::InvalidUniversalString));
+        assert_eq!(Parser::new(&universal[..2]).next().err(), Some(Error::InvalidUniversalString));
+        
+        // Since the header was intact, lenient parsing can skip such a value.
+        let bs = [0x30, 0x0a, 0x1e, 0x03, 0x00, b'h', 0x00, 0x01, 0x01, 0xff, 0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        match parser.next_lenient() {
+            (Some(Asn1Value::Boolean(true)), ref errors) if errors[..] == [Error::InvalidBMPString] => {},
+            _ => { panic!("Expected the BMPString to be skipped"); }
+        }
+    }
+
+    #[test]
+    fn every_dispatched_tag_is_implemented() {
+        // A minimal valid value for each universal tag that next() dispatches on.
+        let samples: [&[u8]; 18] = [
+            &[0x01, 0x01, 0x00],
+            &[0x02, 0x01, 0x00],
+            &[0x03, 0x01, 0x00],
+            &[0x04, 0x00],
+            &[0x05, 0x00],
+            &[0x06, 0x01, 0x2a],
+            &[0x09, 0x00],
+            &[0x0a, 0x01, 0x00],
+            &[0x0c, 0x00],
+            &[0x13, 0x00],
+            &[0x16, 0x00],
+            &[0x17, 0x0d, b'2', b'4', b'0', b'1', b'0', b'1', b'0', b'0', b'0', b'0', b'0', b'0', b'Z'],
+            &[0x18, 0x0f, b'2', b'0', b'2', b'4', b'0', b'1', b'0', b'1', b'0', b'0', b'0', b'0', b'0', b'0', b'Z'],
+            &[0x1b, 0x00],
+            &[0x1c, 0x00],
+            &[0x1e, 0x00],
+            &[0x30, 0x00],
+            &[0x31, 0x00],
+        ];
+        for sample in samples.iter() {
+            if let Err(error) = Parser::new(sample).next() {
+                panic!("Tag {:#04x} failed with {:?}", sample[0], error);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_handlers() {
+        let bs = [0x60, 0x03, 0x02, 0x01, 0x2a,
+                  0x0e, 0x01, 0x07,
+                  0x61, 0x00];
+        let mut parser = Parser::new(&bs);
+        parser.set_custom_handler(0x60, Box::new(|content| {
+            let value = try!(Parser::new(content).next_integer()).as_u32();
+            Ok(Box::new(try!(value.ok_or(Error::Malformed))))
+        }));
+        parser.set_custom_handler(0x0e, Box::new(|content| Ok(Box::new(content.to_vec()))));
+        
+        match parser.next().unwrap() {
+            Asn1Value::Custom{ tag: 0x60, value } => { assert_eq!(value.downcast_ref::<u32>(), Some(&42)); },
+            _ => { panic!("Expected a custom value"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::Custom{ tag: 0x0e, value } => { assert_eq!(value.downcast_ref::<Vec<u8>>(), Some(&vec![7])); },
+            _ => { panic!("Expected a custom value"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::TaggedStart(TagClass::Application, 1) => {},
+            _ => { panic!("Expected an application tag"); }
+        }
+        
+        // Built-in types aren't overridden.
+        let mut parser = Parser::new(&bs[2..5]);
+        parser.set_custom_handler(0x02, Box::new(|_| Err(Error::Malformed)));
+        assert!(parser.next_integer().is_ok());
+    }
+
+    #[test]
+    fn general_string() {
+        let bs = [0x1b, 0x04, 0x1b, 0x28, 0x42, b'a'];
+        match Parser::new(&bs).next().unwrap() {
+            Asn1Value::GeneralString(x) => { assert_eq!(x, &bs[2..]); },
+            _ => { panic!("Expected a GeneralString"); }
+        }
+        
+        let mut out = Vec::new();
+        Asn1Value::GeneralString(&bs[2..]).encode(&mut out).unwrap();
+        assert_eq!(&out[..], &bs[..]);
+    }
+
+    #[test]
+    fn control_chars() {
+        let nul = [0x0c, 0x03, b'a', 0x00, b'b'];
+        let next_line = [0x0c, 0x03, b'a', 0xc2, 0x85];
+        let whitespace = [0x0c, 0x03, b'a', b'\t', b'\n'];
+        
+        assert!(Parser::new(&nul).next().is_ok());
+        for bs in [&nul[..], &next_line[..]].iter() {
+            let mut parser = Parser::new(bs);
+            parser.set_reject_control_chars(true);
+            assert_eq!(parser.next().err(), Some(Error::DisallowedControlCharacter));
+        }
+        
+        let mut parser = Parser::new(&whitespace);
+        parser.set_reject_control_chars(true);
+        match parser.next().unwrap() {
+            Asn1Value::Utf8String(x) => { assert_eq!(x, "a\t\n"); },
+            _ => { panic!("Expected a UTF8String"); }
+        }
+    }
+
+    #[test]
+    fn into_inner() {
+        let bs = [0x02, 0x01, 0x07, 0xde, 0xad];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next_integer().is_ok());
+        let (input, position) = parser.into_inner();
+        assert_eq!(input, &bs[..]);
+        assert_eq!(position, 3);
+        assert_eq!(&input[position..], [0xde, 0xad]);
+    }
+
+    #[test]
+    fn max_values() {
+        let nulls = [0x05, 0x00].repeat(1000);
+        let mut parser = Parser::new(&nulls);
+        parser.set_max_values(100);
+        for _ in 0..100 {
+            assert!(parser.next().is_ok());
+        }
+        assert_eq!(parser.next().err(), Some(Error::TooManyValues));
+        
+        // End markers don't count towards the limit.
+        let bs = [0x30, 0x02, 0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        parser.set_max_values(2);
+        assert!(parser.next().is_ok());
+        assert!(parser.next().is_ok());
+        match parser.next() {
+            Ok(Asn1Value::SequenceEnd) => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        // A call that fails doesn't count, so skipping the bad value and retrying works.
+        let bs = [0x13, 0x01, b'*', 0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        parser.set_max_values(1);
+        match parser.next_lenient() {
+            (Some(Asn1Value::Null), ref errors) if errors[..] == [Error::InvalidPrintableString] => {},
+            _ => { panic!("Expected a null after one error"); }
+        }
+    }
+
+    #[test]
+    fn documents() {
+        let bs = [0x30, 0x03, 0x02, 0x01, 0x07, 0x00, 0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        let mut first = parser.next_document().unwrap().unwrap();
+        match first.next() {
+            Ok(Asn1Value::SequenceStart) => {},
+            _ => { panic!("Expected a sequence"); }
+        }
+        assert_eq!(first.next_integer().unwrap().as_u8(), Some(7));
+        // By default, the padding byte is taken as the start of another document.
+        assert_eq!(parser.next_document().err(), Some(Error::EOF));
+        
+        let mut parser = Parser::new(&bs);
+        parser.set_skip_interdocument_padding(true);
+        assert!(parser.next_document().unwrap().is_some());
+        let mut second = parser.next_document().unwrap().unwrap();
+        match second.next() {
+            Ok(Asn1Value::Null) => {},
+            _ => { panic!("Expected a null"); }
+        }
+        assert!(parser.next_document().unwrap().is_none());
+    }
+
+    #[test]
+    fn framed() {
+        let bs = [0x30, 0x03, 0x02, 0x01, 0x07, 0x30, 0x00];
+        let (mut parser, rest) = Parser::new_framed(&bs).unwrap();
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(7));
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        assert_eq!(rest, &[0x30, 0x00]);
+        
+        assert_eq!(Parser::new_framed(&bs[..4]).err(), Some(Error::EOF));
+        assert_eq!(Parser::new_framed(&bs[..1]).err(), Some(Error::EOF));
+        assert_eq!(Parser::new_framed(&[]).err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn any() {
+        let bs = [0x30, 0x0c,
+                  0x02, 0x01, 0x01,
+                  0x30, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff,
+                  0x05, 0x00,
+                  0x04, 0x01];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert!(parser.next_integer().is_ok());
+        assert_eq!(parser.read_any(), Ok(&bs[5..12]));
+        assert_eq!(parser.read_any(), Ok(&bs[12..14]));
+        assert_eq!(parser.read_any(), Err(Error::EndOfStructure));
+        assert!(parser.next().is_ok());
+        
+        assert_eq!(parser.read_any(), Err(Error::EOF));
+        assert_eq!(parser.consumed(), 14);
+    }
+
+    #[test]
+    fn rest_of_structure() {
+        let bs = [0x30, 0x0a,
+                  0x06, 0x03, 0x2b, 0x65, 0x70,
+                  0x02, 0x01, 0x05,
+                  0x05, 0x00,
+                  0x01, 0x01, 0xff];
+        let mut parser = Parser::new(&bs);
+        assert!(parser.next().is_ok());
+        assert!(parser.next_object_identifier().is_ok());
+        
+        assert_eq!(parser.consumed(), 7);
+        
+        let mut rest = parser.take_rest_of_structure().unwrap();
+        assert_eq!(parser.consumed(), 12);
+        assert_eq!(rest.consumed(), 0);
+        assert_eq!(rest.next_integer().unwrap().as_u8(), Some(5));
+        assert_eq!(rest.consumed(), 3);
+        assert_eq!(rest.next_null(), Ok(()));
+        assert_eq!(rest.next().err(), Some(Error::EOF));
+        
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        assert_eq!(parser.next_boolean(), Ok(true));
+    }
+
+    #[test]
+    fn octet_string_as_parser() {
+        let bs = [0x04, 0x05,
+                  0x30, 0x03, 0x02, 0x01, 0x07,
+                  0x05, 0x00];
+        let mut parser = Parser::new(&bs);
+        let mut inner = parser.read_octet_string_as_parser().unwrap();
+        match inner.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        assert_eq!(inner.next_integer().unwrap().as_u8(), Some(7));
+        match inner.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        assert_eq!(inner.next().err(), Some(Error::EOF));
+        
+        // The outer parser carries on after the OCTET STRING.
+        assert_eq!(parser.next_null(), Ok(()));
+        assert_eq!(parser.read_octet_string_as_parser().err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn last_tag() {
+        let bs = [0xa3, 0x03,
+                  0x02, 0x01, 0x05,
+                  0x5f, 0x81, 0x00, 0x00];
+        let mut parser = Parser::new(&bs);
+        assert_eq!(parser.last_tag(), None);
+        
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.last_tag(), Some(Tag{ class: TagClass::ContextSpecific, constructed: true, number: 3 }));
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.last_tag(), Some(Tag{ class: TagClass::Universal, constructed: false, number: 2 }));
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.last_tag(), Some(Tag{ class: TagClass::ContextSpecific, constructed: true, number: 3 }));
+        assert!(parser.next().is_ok());
+        assert_eq!(parser.last_tag(), Some(Tag{ class: TagClass::Application, constructed: false, number: 128 }));
+        assert!(parser.next().is_err());
+        assert_eq!(parser.last_tag(), None);
+    }
+
+    #[test]
+    fn empty_structures() {
+        fn expect_values(bs: &[u8], expected: &[&str]) {
+            let mut parser = Parser::new(bs);
+            for name in expected {
+                let actual = match parser.next().unwrap() {
+                    Asn1Value::SequenceStart => "SequenceStart",
+                    Asn1Value::SequenceEnd => "SequenceEnd",
+                    Asn1Value::SetStart => "SetStart",
+                    Asn1Value::SetEnd => "SetEnd",
+                    Asn1Value::Null => "Null",
+                    _ => "other",
+                };
+                assert_eq!(actual, *name);
+            }
+            assert_eq!(parser.next().err(), Some(Error::EOF));
+        }
+        
+        expect_values(&[0x30, 0x00], &["SequenceStart", "SequenceEnd"]);
+        expect_values(&[0x31, 0x00], &["SetStart", "SetEnd"]);
+        expect_values(&[0x30, 0x02, 0x30, 0x00],
+                      &["SequenceStart", "SequenceStart", "SequenceEnd", "SequenceEnd"]);
+        expect_values(&[0x30, 0x04, 0x31, 0x00, 0x05, 0x00],
+                      &["SequenceStart", "SetStart", "SetEnd", "Null", "SequenceEnd"]);
+        expect_values(&[0x30, 0x04, 0x05, 0x00, 0x30, 0x00],
+                      &["SequenceStart", "Null", "SequenceStart", "SequenceEnd", "SequenceEnd"]);
+        expect_values(&[0x30, 0x00, 0x31, 0x00],
+                      &["SequenceStart", "SequenceEnd", "SetStart", "SetEnd"]);
+    }
+
+    #[test]
+    fn content_after_empty_structure() {
+        // At the top level, what follows an empty SEQUENCE is just the next value.
+        let bs = [0x30, 0x00, 0x02, 0x01, 0x05];
+        let mut parser = Parser::new(&bs);
+        match parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected sequence start"); }
+        }
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected sequence end"); }
+        }
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(5));
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        // Nested, it belongs to the parent, which it overruns.
+        let nested = [0x30, 0x04, 0x30, 0x00, 0x02, 0x01, 0x05];
+        let mut parser = Parser::new(&nested);
+        assert!(parser.next().is_ok());
+        assert!(parser.next().is_ok());
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected inner sequence end"); }
+        }
+        assert_eq!(parser.next().err(), Some(Error::StructureOverrun));
+    }
+
+    fn run_to_completion(bs: &[u8]) {
+        let mut parser = Parser::new(bs);
+        while let Ok(value) = parser.next() {
+            if let Asn1Value::ObjectIdentifier(ref oid) = value {
+                for _ in oid.iter() {}
+            }
+            let mut out = Vec::new();
+            let _ = value.encode(&mut out);
+        }
+    }
+
+    #[test]
+    fn arbitrary_input_does_not_panic() {
+        for x in 0..0x10000u32 {
+            run_to_completion(&[(x >> 8) as u8, x as u8]);
+        }
+        
+        let sample = [0x30, 0x1b, 0x06, 0x03, 0x2a, 0x03, 0x04, 0x02, 0x01, 0xff, 0x03, 0x02, 0x04, 0x10,
+                      0x31, 0x06, 0xa0, 0x04, 0x13, 0x02, b'h', b'i', 0x17, 0x0d, b'2', b'4', b'0', b'6',
+                      b'3', b'0'];
+        for end in 0..sample.len() + 1 {
+            run_to_completion(&sample[..end]);
+        }
+        for idx in 0..sample.len() {
+            for replacement in 0..0x100u32 {
+                let mut mutated = sample;
+                mutated[idx] = replacement as u8;
+                run_to_completion(&mutated);
+            }
+        }
+    }
+
 }
@@ -1,56 +1,112 @@
 use error::Error;
 
+#[derive(Copy, Clone)]
 pub struct ObjectIdentifier<'a> {
     content: &'a [u8],
 }
 
-impl<'a> ObjectIdentifier<'a> {
-    pub fn new(content: &'a [u8]) -> Result<ObjectIdentifier<'a>, Error> {
-        let mut iter = content.iter();
-        
-        // The initial byte encodes the first two digits as x*40 + y, where x<3 and y<40
-        match iter.next() {
-            Some(first) => {
-                if *first >= 3 * 40 {
-                    return Err(Error::MalformedObjectIdentifier)
-                }
+/// Checks that `content` is a canonical OID encoding, with no component longer than
+/// `max_component_length` bytes, where the first byte of a component that long carries a
+/// value of at most `max_leading_bits`.
+fn check_encoding(content: &[u8], max_component_length: usize, max_leading_bits: u8) -> Result<(), Error> {
+    let mut iter = content.iter();
+    
+    // The initial byte encodes the first two digits as x*40 + y, where x<3 and y<40
+    match iter.next() {
+        Some(first) => {
+            if *first >= 3 * 40 {
+                return Err(Error::MalformedObjectIdentifier)
             }
-            None => { return Err(Error::MalformedObjectIdentifier) },
         }
-        
-        // We want to make sure that no digit represented in this OID will overflow a u32.
-        // Allowing byte sequences for a single digit to be only up to 4 bytes long
-        // accomplishes that. It actually only allows 7+7+7+8 = 29 bits per digit, but that is
-        // larger than any reasonable digit.
-        let mut current_length = 0;
-        for x in iter {
-            if *x & 0x80 == 0 {
-                current_length = 0;
-            } else {
-                if current_length==0 && *x == 0x80 {
-                    return Err(Error::MalformedObjectIdentifier); // This byte was not needed!
-                }
-                
-                current_length += 1;
-                if current_length > 4 {
-                    return Err(Error::ObjectIdentifierTooLarge);
-                }
+        None => { return Err(Error::MalformedObjectIdentifier) },
+    }
+    
+    let mut current_length = 0;
+    let mut current_first = 0;
+    for x in iter {
+        if current_length == 0 {
+            if *x == 0x80 {
+                return Err(Error::MalformedObjectIdentifier); // This byte was not needed!
             }
+            current_first = *x & 0x7f;
         }
         
-        if current_length != 0 {
-            // We are in the middle of a digit!
-            return Err(Error::MalformedObjectIdentifier);
+        current_length += 1;
+        if current_length > max_component_length
+            || (current_length == max_component_length && current_first > max_leading_bits) {
+            return Err(Error::ObjectIdentifierTooLarge);
         }
         
+        if *x & 0x80 == 0 {
+            current_length = 0;
+        }
+    }
+    
+    if current_length != 0 {
+        // We are in the middle of a digit!
+        return Err(Error::MalformedObjectIdentifier);
+    }
+    Ok(())
+}
+
+impl<'a> ObjectIdentifier<'a> {
+    /// Checks that `content` is a canonical OID encoding whose components all fit in a u32.
+    ///
+    /// Returns `MalformedObjectIdentifier` if the content is empty, the first byte doesn't
+    /// encode two valid leading components, any component starts with a redundant 0x80 byte,
+    /// or the content ends partway through a component. Returns `ObjectIdentifierTooLarge` if
+    /// a (well-formed) component doesn't fit in a u32.
+    pub fn new(content: &'a [u8]) -> Result<ObjectIdentifier<'a>, Error> {
+        // Each byte carries 7 bits, so a component may take up to 5 bytes, as long as the
+        // first of them carries no more than 32 - 4*7 = 4 bits.
+        try!(check_encoding(content, 5, 0x0f));
         Ok(ObjectIdentifier{ content: content })
     }
 
+    /// Like `new`, but allows components of up to 9 bytes (63 bits), for OIDs with arcs too
+    /// large for a u32, such as UUID-based ones. Read them with `iter_large`, since `iter`
+    /// stops at the first component that doesn't fit in a u32 (reporting it through
+    /// `ObjectIdentifierIterator::overflowed`).
+    pub fn new_large(content: &'a [u8]) -> Result<ObjectIdentifier<'a>, Error> {
+        try!(check_encoding(content, 9, 0x7f));
+        Ok(ObjectIdentifier{ content: content })
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.content
+    }
+
     pub fn iter(&self) -> ObjectIdentifierIterator<'a> {
         ObjectIdentifierIterator{
             content: self.content,
             state: ObjectIdentifierIteratorState::First,
+            overflowed: false,
+        }
+    }
+
+    /// Iterates over the components as u64s, for OIDs from `new_large`.
+    pub fn iter_large(&self) -> LargeObjectIdentifierIterator<'a> {
+        LargeObjectIdentifierIterator(self.iter())
+    }
+
+    /// Whether this is the OID written as `s`, such as "1.2.840.113549". A malformed string
+    /// just compares unequal. Components are compared as u64s, so this works for OIDs from
+    /// `new_large` too.
+    pub fn equals_dotted(&self, s: &str) -> bool {
+        let mut arcs = self.iter();
+        for part in s.split('.') {
+            let well_formed = !part.is_empty() && part.bytes().all(|x| x.is_ascii_digit())
+                && !(part.len() > 1 && part.starts_with('0'));
+            if !well_formed {
+                return false;
+            }
+            
+            match (part.parse::<u64>(), arcs.next_component(u64::MAX)) {
+                (Ok(expected), Some(arc)) if expected == arc => {},
+                _ => { return false; }
+            }
         }
+        arcs.next_component(u64::MAX).is_none() && !arcs.overflowed()
     }
 }
 
@@ -61,15 +117,22 @@ pub enum ObjectIdentifierIteratorState {
     Later
 }
 
+#[derive(Clone)]
 pub struct ObjectIdentifierIterator<'a> {
     content: &'a [u8],
     state: ObjectIdentifierIteratorState,
+    overflowed: bool,
 }
 
-impl<'a> Iterator for ObjectIdentifierIterator<'a> {
-    type Item = u32;
-    
-    fn next(&mut self) -> Option<u32> {
+impl<'a> ObjectIdentifierIterator<'a> {
+    /// Whether iteration ended early, at a component too large to return. Only OIDs from
+    /// `new_large` can have components too large for a u32.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Decodes the next component, stopping (rather than wrapping) at one above `max`.
+    fn next_component(&mut self, max: u64) -> Option<u64> {
         let first = if let Some(first) = self.content.first() { 
             *first 
         } else {
@@ -79,18 +142,25 @@ impl<'a> Iterator for ObjectIdentifierIterator<'a> {
         match self.state {
             ObjectIdentifierIteratorState::First => {
                 self.state = ObjectIdentifierIteratorState::Second;
-                return Some((first / 40) as u32);
+                return Some((first / 40) as u64);
             },
             ObjectIdentifierIteratorState::Second => {
                 self.state = ObjectIdentifierIteratorState::Later;
                 self.content = &self.content[1..];
-                return Some((first % 40) as u32);
+                return Some((first % 40) as u64);
             }
             ObjectIdentifierIteratorState::Later => {
-                let mut accumulator = 0;
+                let mut accumulator = 0u64;
                 
                 for (idx, byte) in self.content.iter().enumerate() {
-                    accumulator = (accumulator<<7) | ((*byte as u32) & 0x7f);
+                    // ObjectIdentifier::new rejects components that are too large, but
+                    // rather than rely on that, stop instead of yielding a wrapped value.
+                    if accumulator > (max >> 7) {
+                        self.content = &[];
+                        self.overflowed = true;
+                        return None;
+                    }
+                    accumulator = (accumulator<<7) | ((*byte as u64) & 0x7f);
                     if (*byte & 0x80)==0 {
                         self.content = &self.content[idx+1..];
                         return Some(accumulator)
@@ -98,13 +168,32 @@ impl<'a> Iterator for ObjectIdentifierIterator<'a> {
                 }
                 
                 // This is malformed, since it did not end with a high-bit-off byte!
-                // The ObjectIdentifier initializer should have caught that.
-                unreachable!();
+                // The ObjectIdentifier initializer should have caught that, but stop cleanly.
+                self.content = &[];
+                None
             }
         }
     }
 }
 
+impl<'a> Iterator for ObjectIdentifierIterator<'a> {
+    type Item = u32;
+    
+    fn next(&mut self) -> Option<u32> {
+        self.next_component(u32::MAX as u64).map(|x| x as u32)
+    }
+}
+
+#[derive(Clone)]
+pub struct LargeObjectIdentifierIterator<'a>(ObjectIdentifierIterator<'a>);
+
+impl<'a> Iterator for LargeObjectIdentifierIterator<'a> {
+    type Item = u64;
+    
+    fn next(&mut self) -> Option<u64> {
+        self.0.next_component(u64::MAX)
+    }
+}
 
 #[test]
 fn oids() {
@@ -136,3 +225,114 @@ fn oids() {
     bad_oid(&[0x00, 0x80, 0x01]); // The 0x80 is unnecessary
 }
 
+#[test]
+fn dotted_comparison() {
+    let oid = ObjectIdentifier::new(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]).unwrap();
+    assert!(oid.equals_dotted("1.2.840.113549.1.1.1"));
+    for wrong in ["1.2.840.113549.1.1.11", "1.2.840.113549.1.1", "1.2.840.113549.1.1.1.0",
+                  "1.2.840.113549.1.1.01", "1.2.840..113549.1.1.1", "1.2.840.113549.1.1.1.",
+                  "1.2.840.+113549.1.1.1", "1.2.840.99999999999.1.1.1", ""].iter() {
+        assert!(!oid.equals_dotted(wrong), "{}", wrong);
+    }
+    
+    let microsoft = ObjectIdentifier::new(&[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x14]).unwrap();
+    assert!(microsoft.equals_dotted("1.3.6.1.4.1.311.21.20"));
+    assert!(!microsoft.equals_dotted("1.2.840.113549.1.1.1"));
+    
+    // A final arc too large for a u32 still has to match.
+    let large = ObjectIdentifier::new_large(&[0x69, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).unwrap();
+    assert!(large.equals_dotted("2.25.9223372036854775807"));
+    assert!(!large.equals_dotted("2.25"));
+    assert!(!large.equals_dotted("2.25.4294967295"));
+    assert!(!large.equals_dotted("2.25.9223372036854775806"));
+}
+
+#[test]
+fn large_components() {
+    // 2.25.<a 64-bit UUID-style arc>, whose last component takes 9 bytes.
+    let bs = [0x69, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+    assert_eq!(ObjectIdentifier::new(&bs).err(), Some(Error::ObjectIdentifierTooLarge));
+    
+    let oid = ObjectIdentifier::new_large(&bs).unwrap();
+    let digits: Vec<u64> = oid.iter_large().collect();
+    assert_eq!(digits, [2, 25, (1 << 63) - 1]);
+    // The u32 iterator can't return the last component, and says so.
+    let mut arcs = oid.iter();
+    let digits: Vec<u32> = arcs.by_ref().collect();
+    assert_eq!(digits, [2, 25]);
+    assert!(arcs.overflowed());
+    
+    let mut arcs = ObjectIdentifier::new_large(&[0x69, 0x01]).unwrap().iter();
+    assert_eq!(arcs.by_ref().count(), 3);
+    assert!(!arcs.overflowed());
+    
+    // A 5-byte component too large for a u32.
+    let bs = [0x2a, 0x90, 0x80, 0x80, 0x80, 0x00];
+    let digits: Vec<u64> = ObjectIdentifier::new_large(&bs).unwrap().iter_large().collect();
+    assert_eq!(digits, [1, 2, 1 << 32]);
+    
+    let too_long = [0x2a, 0x81, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+    assert_eq!(ObjectIdentifier::new_large(&too_long).err(), Some(Error::ObjectIdentifierTooLarge));
+}
+
+#[test]
+fn copies() {
+    let oid = ObjectIdentifier::new(&[0x2b, 0x06, 0x01]).unwrap();
+    let copy = oid;
+    assert_eq!(oid.as_bytes(), copy.as_bytes());
+    
+    let mut iter = copy.iter();
+    iter.next();
+    let rest: Vec<u32> = iter.clone().collect();
+    assert_eq!(rest, [3, 6, 1]);
+    assert_eq!(iter.count(), 3);
+}
+
+#[test]
+fn oversized_component_is_not_wrapped() {
+    // Bypasses the checks in new.
+    let oid = ObjectIdentifier{ content: &[0x2a, 0x03, 0x90, 0x80, 0x80, 0x80, 0x00, 0x05] };
+    let mut arcs = oid.iter();
+    let digits: Vec<u32> = arcs.by_ref().collect();
+    assert_eq!(digits, [1, 2, 3]);
+    assert!(arcs.overflowed());
+}
+
+#[test]
+fn truncated_component_ends_iteration() {
+    // Bypasses the checks in new.
+    let oid = ObjectIdentifier{ content: &[0x2a, 0x03, 0x90, 0x80] };
+    let digits: Vec<u32> = oid.iter().collect();
+    assert_eq!(digits, [1, 2, 3]);
+}
+
+#[test]
+fn oid_errors() {
+    fn error_of(bytes: &[u8]) -> Option<Error> {
+        ObjectIdentifier::new(bytes).err()
+    }
+    
+    let cases: &[(&[u8], Option<Error>)] = &[
+        (&[], Some(Error::MalformedObjectIdentifier)),
+        (&[0x78], Some(Error::MalformedObjectIdentifier)), // First byte would be 3.0
+        (&[0x2a, 0x80, 0x01], Some(Error::MalformedObjectIdentifier)), // Leading 0x80 in the first later component
+        (&[0x2a, 0x01, 0x80, 0x01], Some(Error::MalformedObjectIdentifier)), // ... in a middle component
+        (&[0x2a, 0x01, 0x80, 0x80, 0x01], Some(Error::MalformedObjectIdentifier)), // ... followed by another 0x80
+        (&[0x2a, 0x81, 0x80, 0x01], None), // 0x80 in the middle of a component is fine
+        (&[0x2a, 0x81], Some(Error::MalformedObjectIdentifier)), // Ends mid-component
+        (&[0x2a, 0x01, 0xff], Some(Error::MalformedObjectIdentifier)),
+        (&[0x2a, 0xff, 0xff, 0xff, 0x7f], None), // 28 bits
+        (&[0x2a, 0x8f, 0xff, 0xff, 0xff, 0x7f], None), // 32 bits: u32::MAX
+        (&[0x2a, 0x90, 0x80, 0x80, 0x80, 0x00], Some(Error::ObjectIdentifierTooLarge)), // 33 bits
+        (&[0x2a, 0x81, 0x80, 0x80, 0x80, 0x80, 0x00], Some(Error::ObjectIdentifierTooLarge)),
+        (&[0x2a, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01], Some(Error::MalformedObjectIdentifier)),
+    ];
+    
+    for &(bytes, expected) in cases {
+        assert_eq!(error_of(bytes), expected, "for {:?}", bytes);
+    }
+    
+    let max: Vec<u32> = ObjectIdentifier::new(&[0x2a, 0x8f, 0xff, 0xff, 0xff, 0x7f]).unwrap().iter().collect();
+    assert_eq!(max, [1, 2, 0xffffffff]);
+}
+
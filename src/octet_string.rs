@@ -0,0 +1,62 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The content of an OCTET STRING, with helpers for the formats commonly carried in one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OctetString<'a>(&'a [u8]);
+
+impl<'a> OctetString<'a> {
+    pub fn new(bytes: &'a [u8]) -> OctetString<'a> {
+        OctetString(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Reads the content as an IP address, as in a SubjectAltName's iPAddress: 4 bytes for
+    /// IPv4 or 16 for IPv6. Any other length gives None.
+    pub fn as_ip_addr(&self) -> Option<IpAddr> {
+        match self.0.len() {
+            4 => {
+                let mut octets = [0u8; 4];
+                octets.copy_from_slice(self.0);
+                Some(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(self.0);
+                Some(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<&'a [u8]> for OctetString<'a> {
+    fn from(bytes: &'a [u8]) -> OctetString<'a> {
+        OctetString::new(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OctetString;
+    use ::Parser;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn ip_addresses() {
+        let bs = [0x04, 0x04, 192, 0, 2, 1];
+        let content = Parser::new(&bs).next_octet_string().unwrap();
+        assert_eq!(OctetString::from(content).as_ip_addr(), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+        
+        let v6 = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01];
+        let expected = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        assert_eq!(OctetString::new(&v6).as_ip_addr(), Some(IpAddr::V6(expected)));
+        
+        assert_eq!(OctetString::new(&[10, 0, 0]).as_ip_addr(), None);
+        assert_eq!(OctetString::new(&[]).as_ip_addr(), None);
+        // A SAN iPAddress constraint (address and mask) is 8 bytes, which isn't an address.
+        assert_eq!(OctetString::new(&[10, 0, 0, 0, 255, 0, 0, 0]).as_ip_addr(), None);
+    }
+}
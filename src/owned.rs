@@ -0,0 +1,260 @@
+use ::{Asn1Value, Parser, MAX_NESTING_DEPTH};
+use error::Error;
+use tag::TagClass;
+use time::DateTime;
+use std::mem;
+use std::ops::Range;
+
+/// A primitive value that owns its content, so it can outlive the input it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedAsn1Value {
+    Null,
+    Boolean(bool),
+    /// The two's-complement content bytes.
+    Integer(Vec<u8>),
+    Enumerated(Vec<u8>),
+    Real(f64),
+    BitString { unused_bits: u8, data: Vec<u8> },
+    /// The encoded components, as in `ObjectIdentifier::as_bytes`.
+    ObjectIdentifier(Vec<u8>),
+    OctetString(Vec<u8>),
+    PrintableString(String),
+    Utf8String(String),
+    Ia5String(String),
+    BmpString(String),
+    UniversalString(String),
+    GeneralString(Vec<u8>),
+    UtcTime(DateTime),
+    GeneralizedTime(DateTime),
+    Tagged(TagClass, u32, Vec<u8>),
+    Unknown { tag: u8, content: Vec<u8> },
+}
+
+impl OwnedAsn1Value {
+    /// Copies a primitive value. Structure markers and custom values give None.
+    pub fn from_value(value: &Asn1Value) -> Option<OwnedAsn1Value> {
+        Some(match *value {
+            Asn1Value::Null => OwnedAsn1Value::Null,
+            Asn1Value::Boolean(x) => OwnedAsn1Value::Boolean(x),
+            Asn1Value::Integer(ref x) => OwnedAsn1Value::Integer(x.as_bytes().to_vec()),
+            Asn1Value::Enumerated(ref x) => OwnedAsn1Value::Enumerated(x.as_bytes().to_vec()),
+            Asn1Value::Real(x) => OwnedAsn1Value::Real(x),
+            Asn1Value::BitString(ref x) => OwnedAsn1Value::BitString{
+                unused_bits: x.unused_bits(),
+                data: x.data().to_vec(),
+            },
+            Asn1Value::ObjectIdentifier(ref x) => OwnedAsn1Value::ObjectIdentifier(x.as_bytes().to_vec()),
+            Asn1Value::OctetString(x) => OwnedAsn1Value::OctetString(x.to_vec()),
+            Asn1Value::PrintableString(x) => OwnedAsn1Value::PrintableString(x.to_string()),
+            Asn1Value::Utf8String(x) => OwnedAsn1Value::Utf8String(x.to_string()),
+            Asn1Value::Ia5String(x) => OwnedAsn1Value::Ia5String(x.to_string()),
+            Asn1Value::BmpString(ref x) => OwnedAsn1Value::BmpString(x.clone()),
+            Asn1Value::UniversalString(ref x) => OwnedAsn1Value::UniversalString(x.clone()),
+            Asn1Value::GeneralString(x) => OwnedAsn1Value::GeneralString(x.to_vec()),
+            Asn1Value::UtcTime(x) => OwnedAsn1Value::UtcTime(x),
+            Asn1Value::GeneralizedTime(x) => OwnedAsn1Value::GeneralizedTime(x),
+            Asn1Value::Tagged(class, number, content) => OwnedAsn1Value::Tagged(class, number, content.to_vec()),
+            Asn1Value::Unknown{ tag, content } => OwnedAsn1Value::Unknown{ tag: tag, content: content.to_vec() },
+            Asn1Value::SequenceStart | Asn1Value::SequenceEnd |
+            Asn1Value::SetStart | Asn1Value::SetEnd |
+            Asn1Value::TaggedStart(..) | Asn1Value::TaggedEnd |
+            Asn1Value::UnknownStart{ .. } | Asn1Value::UnknownEnd |
+            Asn1Value::Custom{ .. } => { return None; }
+        })
+    }
+}
+
+impl<'a> Asn1Value<'a> {
+    /// Keeps a copy of a primitive value by appending its encoding to `buf`, returning where
+    /// it went. Parsing that range of `buf` gives the value back. When converting many values,
+    /// this reuses one buffer, where `OwnedAsn1Value::from_value` allocates for each.
+    pub fn to_owned_in(&self, buf: &mut Vec<u8>) -> Result<Range<usize>, Error> {
+        let start = buf.len();
+        if let Err(error) = self.encode(buf) {
+            buf.truncate(start);
+            return Err(error);
+        }
+        Ok(start..buf.len())
+    }
+}
+
+/// What sort of structure an `OwnedNode::Constructed` is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    Sequence,
+    Set,
+    Tagged(TagClass, u32),
+    Unknown(u8),
+}
+
+/// A whole value, with any structure's contents included as children.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedNode {
+    Leaf(OwnedAsn1Value),
+    Constructed { kind: NodeKind, children: Vec<OwnedNode> },
+}
+
+/// Parses `input`, which must be exactly one value, into a tree that doesn't borrow from it.
+///
+/// A small input can still describe a large tree, so the tree's size is limited to
+/// `max_owned_bytes`: each node counts `size_of::<OwnedNode>()` plus the length of any content
+/// it copies. Reaching a value that would go over the limit gives `AllocationBudgetExceeded`,
+/// before that value is copied. Structures nested more than `MAX_NESTING_DEPTH` deep give
+/// `NestingTooDeep`.
+pub fn parse_to_owned_tree(input: &[u8], max_owned_bytes: usize) -> Result<OwnedNode, Error> {
+    let mut parser = Parser::new(input);
+    let mut budget = max_owned_bytes;
+    let first = try!(parser.next());
+    let node = try!(read_node(&mut parser, first, 0, &mut budget));
+    if parser.consumed() != input.len() {
+        return Err(Error::TrailingData);
+    }
+    Ok(node)
+}
+
+/// Reads `value` and, if it starts a structure, everything in it. `depth` is how many
+/// structures enclose `value`.
+fn read_node(parser: &mut Parser, value: Asn1Value, depth: usize, budget: &mut usize) -> Result<OwnedNode, Error> {
+    let cost = mem::size_of::<OwnedNode>() + owned_content_len(&value);
+    *budget = try!(budget.checked_sub(cost).ok_or(Error::AllocationBudgetExceeded));
+    
+    let kind = match value {
+        Asn1Value::SequenceStart => NodeKind::Sequence,
+        Asn1Value::SetStart => NodeKind::Set,
+        Asn1Value::TaggedStart(class, number) => NodeKind::Tagged(class, number),
+        Asn1Value::UnknownStart{ tag } => NodeKind::Unknown(tag),
+        value => {
+            return OwnedAsn1Value::from_value(&value).map(OwnedNode::Leaf).ok_or(Error::UnexpectedType);
+        }
+    };
+    if depth == MAX_NESTING_DEPTH {
+        return Err(Error::NestingTooDeep);
+    }
+    
+    let mut children = Vec::new();
+    while let Some(child) = try!(parser.next_in_structure()) {
+        children.push(try!(read_node(parser, child, depth + 1, budget)));
+    }
+    Ok(OwnedNode::Constructed{ kind: kind, children: children })
+}
+
+/// How many bytes `OwnedAsn1Value::from_value` will copy out of `value`.
+fn owned_content_len(value: &Asn1Value) -> usize {
+    match *value {
+        Asn1Value::Integer(ref x) | Asn1Value::Enumerated(ref x) => x.as_bytes().len(),
+        Asn1Value::BitString(ref x) => x.data().len(),
+        Asn1Value::ObjectIdentifier(ref x) => x.as_bytes().len(),
+        Asn1Value::OctetString(x) | Asn1Value::GeneralString(x) => x.len(),
+        Asn1Value::PrintableString(x) | Asn1Value::Utf8String(x) | Asn1Value::Ia5String(x) => x.len(),
+        Asn1Value::BmpString(ref x) | Asn1Value::UniversalString(ref x) => x.len(),
+        Asn1Value::Tagged(_, _, content) | Asn1Value::Unknown{ content, .. } => content.len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_to_owned_tree, NodeKind, OwnedAsn1Value, OwnedNode};
+    use ::{Asn1Value, Parser, Writer, MAX_NESTING_DEPTH};
+    use error::Error;
+    use tag::TagClass;
+    use std::mem;
+
+    fn nested_sequences(depth: usize) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for _ in 0..depth {
+            writer.begin_sequence();
+        }
+        for _ in 0..depth {
+            writer.end_sequence().unwrap();
+        }
+        writer.into_bytes()
+    }
+
+    #[test]
+    fn tree() {
+        let bs = vec![0x30, 0x0e,
+                  0x02, 0x01, 0x05,
+                  0x30, 0x03, 0x01, 0x01, 0xff,
+                  0x80, 0x01, 0x00,
+                  0x04, 0x01, 0xaa];
+        let tree = parse_to_owned_tree(&bs, usize::MAX);
+        // Nothing in the tree borrows from the input.
+        drop(bs);
+        
+        let leaf = OwnedNode::Leaf;
+        let expected = OwnedNode::Constructed{
+            kind: NodeKind::Sequence,
+            children: vec![
+                leaf(OwnedAsn1Value::Integer(vec![0x05])),
+                OwnedNode::Constructed{
+                    kind: NodeKind::Sequence,
+                    children: vec![leaf(OwnedAsn1Value::Boolean(true))],
+                },
+                leaf(OwnedAsn1Value::Tagged(TagClass::ContextSpecific, 0, vec![0x00])),
+                leaf(OwnedAsn1Value::OctetString(vec![0xaa])),
+            ],
+        };
+        assert_eq!(tree, Ok(expected));
+    }
+
+    #[test]
+    fn scratch_buffer() {
+        let bs = [0x30, 0x08, 0x02, 0x01, 0x05, 0x0c, 0x03, b'a', b'b', b'c'];
+        let mut parser = Parser::new(&bs);
+        let mut buf = Vec::new();
+        let mut ranges = Vec::new();
+        while let Ok(value) = parser.next() {
+            if let Ok(range) = value.to_owned_in(&mut buf) {
+                ranges.push(range);
+            }
+        }
+        assert_eq!(buf, &bs[2..]);
+        
+        match Parser::new(&buf[ranges[1].clone()]).next().unwrap() {
+            Asn1Value::Utf8String(x) => { assert_eq!(x, "abc"); },
+            _ => { panic!("Expected a UTF8String"); }
+        }
+        
+        assert_eq!(Asn1Value::SequenceStart.to_owned_in(&mut buf), Err(Error::UnencodableValue));
+        assert_eq!(buf.len(), 8);
+    }
+
+    #[test]
+    fn allocation_budget() {
+        // Four one-byte OCTET STRINGs in a SEQUENCE.
+        let bs = [0x30, 0x0c, 0x04, 0x01, 0x01, 0x04, 0x01, 0x02, 0x04, 0x01, 0x03, 0x04, 0x01, 0x04];
+        let tree_size = 5*mem::size_of::<OwnedNode>() + 4;
+        assert!(parse_to_owned_tree(&bs, tree_size).is_ok());
+        assert_eq!(parse_to_owned_tree(&bs, tree_size - 1), Err(Error::AllocationBudgetExceeded));
+        assert_eq!(parse_to_owned_tree(&bs, 0), Err(Error::AllocationBudgetExceeded));
+        
+        // A generous budget still doesn't allow unbounded nesting.
+        assert_eq!(parse_to_owned_tree(&nested_sequences(30_000), 1 << 20), Err(Error::NestingTooDeep));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let mut node = parse_to_owned_tree(&nested_sequences(MAX_NESTING_DEPTH), usize::MAX).unwrap();
+        let mut depth = 0;
+        while let OwnedNode::Constructed{ mut children, .. } = node {
+            depth += 1;
+            node = match children.pop() {
+                Some(child) => child,
+                None => { break; }
+            };
+        }
+        assert_eq!(depth, MAX_NESTING_DEPTH);
+        
+        assert_eq!(parse_to_owned_tree(&nested_sequences(MAX_NESTING_DEPTH + 1), usize::MAX), Err(Error::NestingTooDeep));
+        assert_eq!(parse_to_owned_tree(&nested_sequences(30_000), usize::MAX), Err(Error::NestingTooDeep));
+    }
+
+    #[test]
+    fn single_value() {
+        assert_eq!(parse_to_owned_tree(&[0x05, 0x00], usize::MAX), Ok(OwnedNode::Leaf(OwnedAsn1Value::Null)));
+        assert_eq!(parse_to_owned_tree(&[0x05, 0x00, 0x05, 0x00], usize::MAX), Err(Error::TrailingData));
+        assert_eq!(parse_to_owned_tree(&[0x30, 0x02, 0x05], usize::MAX), Err(Error::EOF));
+        assert_eq!(parse_to_owned_tree(&[], usize::MAX), Err(Error::EOF));
+    }
+}
@@ -0,0 +1,150 @@
+use error::Error;
+
+/// Decodes the first PEM block in `input`, returning its label (such as "CERTIFICATE") and
+/// the DER inside it.
+pub fn pem_to_der(input: &str) -> Result<(String, Vec<u8>), Error> {
+    match iter_pem(input).next() {
+        Some(block) => block,
+        None => Err(Error::InvalidPem),
+    }
+}
+
+/// Decodes each `-----BEGIN label-----` ... `-----END label-----` block in `input` in turn,
+/// as in a file holding a certificate chain. Text between blocks is ignored. Iteration
+/// stops after the first block that fails to decode.
+pub fn iter_pem(input: &str) -> impl Iterator<Item = Result<(String, Vec<u8>), Error>> + '_ {
+    PemBlocks{ rest: input }
+}
+
+struct PemBlocks<'a> {
+    rest: &'a str,
+}
+
+impl<'a> PemBlocks<'a> {
+    fn next_block(&mut self) -> Result<(String, Vec<u8>), Error> {
+        let begin = try!(self.rest.find("-----BEGIN ").ok_or(Error::InvalidPem));
+        let after_begin = &self.rest[begin + "-----BEGIN ".len()..];
+        let label_end = try!(after_begin.find("-----").ok_or(Error::InvalidPem));
+        let label = &after_begin[..label_end];
+        let body_and_rest = &after_begin[label_end + "-----".len()..];
+
+        let end_marker = format!("-----END {}-----", label);
+        let body_end = try!(body_and_rest.find(&end_marker).ok_or(Error::InvalidPem));
+        self.rest = &body_and_rest[body_end + end_marker.len()..];
+
+        let der = try!(decode_base64(&body_and_rest[..body_end]));
+        Ok((label.to_string(), der))
+    }
+}
+
+impl<'a> Iterator for PemBlocks<'a> {
+    type Item = Result<(String, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Result<(String, Vec<u8>), Error>> {
+        if !self.rest.contains("-----BEGIN ") {
+            return None;
+        }
+
+        let block = self.next_block();
+        if block.is_err() {
+            self.rest = "";
+        }
+        Some(block)
+    }
+}
+
+/// Decodes standard, padded base64, ignoring whitespace (such as the line breaks in PEM).
+fn decode_base64(text: &str) -> Result<Vec<u8>, Error> {
+    fn sextet(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let chars: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if chars.len() % 4 != 0 {
+        return Err(Error::InvalidPem);
+    }
+
+    let mut out = Vec::with_capacity(chars.len() / 4 * 3);
+    for (idx, group) in chars.chunks(4).enumerate() {
+        let last = idx == chars.len() / 4 - 1;
+        let padding = group.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(Error::InvalidPem);
+        }
+
+        let mut accumulator = 0u32;
+        for c in &group[..4 - padding] {
+            accumulator = (accumulator << 6) | try!(sextet(*c).ok_or(Error::InvalidPem));
+        }
+        accumulator <<= 6 * padding;
+
+        let bytes = [(accumulator >> 16) as u8, (accumulator >> 8) as u8, accumulator as u8];
+        out.extend_from_slice(&bytes[..3 - padding]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_base64, iter_pem, pem_to_der, PemBlocks};
+    use error::Error;
+    use x509::open_certificate;
+
+    #[test]
+    fn chain() {
+        let bundle = "subject=CN=leaf\n\
+                      -----BEGIN CERTIFICATE-----\n\
+                      MBQwAwIBATAFBgMrZXADBgDerb7vAA==\n\
+                      -----END CERTIFICATE-----\n\
+                      subject=CN=root\n\
+                      -----BEGIN CERTIFICATE-----\n\
+                      MAMC\n\
+                      AQI=\n\
+                      -----END CERTIFICATE-----\n";
+        let blocks: Vec<_> = iter_pem(bundle).collect();
+        assert_eq!(blocks.len(), 2);
+
+        let (label, der) = blocks[0].clone().unwrap();
+        assert_eq!(label, "CERTIFICATE");
+        assert!(open_certificate(&der).is_ok());
+
+        assert_eq!(blocks[1], Ok(("CERTIFICATE".to_string(), vec![0x30, 0x03, 0x02, 0x01, 0x02])));
+        assert_eq!(pem_to_der(bundle), blocks[0]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(pem_to_der("no blocks here"), Err(Error::InvalidPem));
+        assert!(iter_pem("").next().is_none());
+        assert_eq!(PemBlocks{ rest: "no blocks here" }.next_block(), Err(Error::InvalidPem));
+
+        // A bad block ends the iteration, even if good ones follow.
+        let bad_then_good = "-----BEGIN KEY-----\nMAM*AQI=\n-----END KEY-----\n\
+                             -----BEGIN KEY-----\nMAMCAQI=\n-----END KEY-----\n";
+        assert_eq!(iter_pem(bad_then_good).collect::<Vec<_>>(), [Err(Error::InvalidPem)]);
+        let missing_end = "-----BEGIN KEY-----\nMAMCAQI=\n-----END CERTIFICATE-----\n";
+        assert_eq!(iter_pem(missing_end).collect::<Vec<_>>(), [Err(Error::InvalidPem)]);
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64(""), Ok(vec![]));
+        assert_eq!(decode_base64("TWFu"), Ok(b"Man".to_vec()));
+        assert_eq!(decode_base64("TWE="), Ok(b"Ma".to_vec()));
+        assert_eq!(decode_base64("TQ=="), Ok(b"M".to_vec()));
+        assert_eq!(decode_base64("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4OTo7"),
+                   Ok((0..60).collect::<Vec<u8>>()));
+
+        assert_eq!(decode_base64("TWF"), Err(Error::InvalidPem));
+        assert_eq!(decode_base64("TQ==TWFu"), Err(Error::InvalidPem));
+        assert_eq!(decode_base64("T==="), Err(Error::InvalidPem));
+        assert_eq!(decode_base64("TW*u"), Err(Error::InvalidPem));
+    }
+}
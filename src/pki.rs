@@ -0,0 +1,231 @@
+use ::{Asn1Value, Parser};
+use error::Error;
+use integer::Integer;
+use object_identifier::ObjectIdentifier;
+use std::convert::TryFrom;
+
+/// Reads an `AlgorithmIdentifier ::= SEQUENCE { algorithm OBJECT IDENTIFIER, parameters ANY
+/// OPTIONAL }`. The parameters, if present, are returned as their whole undecoded encoding.
+pub fn read_algorithm_identifier<'a>(parser: &mut Parser<'a>) -> Result<(ObjectIdentifier<'a>, Option<&'a [u8]>), Error> {
+    read_algorithm_identifier_with(parser, false)
+}
+
+/// Like `read_algorithm_identifier`, but if `require_null_params` is set, the parameters must
+/// be an explicit NULL, as RSA's are. Absent ones give `MissingAlgorithmParameters`, and any
+/// other value `UnexpectedType`.
+pub fn read_algorithm_identifier_with<'a>(parser: &mut Parser<'a>, require_null_params: bool) -> Result<(ObjectIdentifier<'a>, Option<&'a [u8]>), Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let algorithm = try!(parser.next_object_identifier());
+    let parameters = match parser.peek_tag() {
+        Ok(_) => Some(try!(parser.read_any())),
+        Err(Error::EndOfStructure) => None,
+        Err(error) => { return Err(error); }
+    };
+    if require_null_params {
+        match parameters {
+            Some([0x05, 0x00]) => {},
+            Some(_) => { return Err(Error::UnexpectedType); }
+            None => { return Err(Error::MissingAlgorithmParameters); }
+        }
+    }
+    
+    try!(parser.expect_end());
+    Ok((algorithm, parameters))
+}
+
+/// Reads an X.509 `Name`, a SEQUENCE OF RelativeDistinguishedName (each a SET OF
+/// AttributeTypeAndValue), returning the type and value of every attribute in order.
+pub fn read_name<'a>(parser: &mut Parser<'a>) -> Result<Vec<(ObjectIdentifier<'a>, Asn1Value<'a>)>, Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let mut attributes = Vec::new();
+    while let Some(rdn) = try!(parser.next_in_structure()) {
+        match rdn {
+            Asn1Value::SetStart => {},
+            _ => { return Err(Error::UnexpectedType); }
+        }
+        
+        while let Some(attribute) = try!(parser.next_in_structure()) {
+            match attribute {
+                Asn1Value::SequenceStart => {},
+                _ => { return Err(Error::UnexpectedType); }
+            }
+            
+            let attribute_type = try!(parser.next_object_identifier());
+            let value = match try!(parser.next_element()) {
+                Asn1Value::SequenceStart |
+                Asn1Value::SetStart |
+                Asn1Value::TaggedStart(..) |
+                Asn1Value::UnknownStart{..} => { return Err(Error::UnexpectedType); }
+                value => value,
+            };
+            try!(parser.expect_end());
+            attributes.push((attribute_type, value));
+        }
+    }
+    Ok(attributes)
+}
+
+/// Reads an `RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }`, the
+/// content of the BIT STRING in an RSA key's SubjectPublicKeyInfo. Returns the modulus and
+/// the exponent, rejecting negative values of either with `NegativeModulus`.
+pub fn read_rsa_public_key<'a>(parser: &mut Parser<'a>) -> Result<(Integer<'a>, Integer<'a>), Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let modulus = try!(parser.next_integer());
+    let exponent = try!(parser.next_integer());
+    if modulus.is_negative() || exponent.is_negative() {
+        return Err(Error::NegativeModulus);
+    }
+    
+    try!(parser.expect_end());
+    Ok((modulus, exponent))
+}
+
+/// Reads a PKCS#8 `PrivateKeyInfo ::= SEQUENCE { version INTEGER, privateKeyAlgorithm
+/// AlgorithmIdentifier, privateKey OCTET STRING }`, returning the version, the algorithm's
+/// OID and the content of the privateKey, whose format depends on the algorithm.
+pub fn read_private_key_info<'a>(parser: &mut Parser<'a>) -> Result<(i64, ObjectIdentifier<'a>, &'a [u8]), Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let version = try!(i64::try_from(&try!(parser.next_integer())));
+    let (algorithm, _) = try!(read_algorithm_identifier(parser));
+    let private_key = try!(parser.next_octet_string());
+    try!(parser.expect_end());
+    Ok((version, algorithm, private_key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_algorithm_identifier, read_algorithm_identifier_with, read_name, read_private_key_info, read_rsa_public_key};
+    use ::{Asn1Value, Parser};
+    use algorithm::KnownOid;
+    use error::Error;
+
+    #[test]
+    fn algorithm_identifier() {
+        let rsa = [0x30, 0x0d,
+                   0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+                   0x05, 0x00];
+        let mut parser = Parser::new(&rsa);
+        let (algorithm, parameters) = read_algorithm_identifier(&mut parser).unwrap();
+        assert_eq!(KnownOid::from_oid(&algorithm), KnownOid::RsaEncryption);
+        assert_eq!(parameters, Some(&[0x05, 0x00][..]));
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let ed25519 = [0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70];
+        let (algorithm, parameters) = read_algorithm_identifier(&mut Parser::new(&ed25519)).unwrap();
+        assert_eq!(KnownOid::from_oid(&algorithm), KnownOid::Ed25519);
+        assert_eq!(parameters, None);
+        
+        let extra = [0x30, 0x09, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x05, 0x00, 0x05, 0x00];
+        assert_eq!(read_algorithm_identifier(&mut Parser::new(&extra)).err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn null_parameters() {
+        let present = [0x30, 0x0d,
+                       0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+                       0x05, 0x00];
+        let (_, parameters) = read_algorithm_identifier_with(&mut Parser::new(&present), true).unwrap();
+        assert_eq!(parameters, Some(&[0x05, 0x00][..]));
+        
+        let absent = [0x30, 0x0b,
+                      0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+        assert_eq!(read_algorithm_identifier_with(&mut Parser::new(&absent), true).err(),
+                   Some(Error::MissingAlgorithmParameters));
+        assert!(read_algorithm_identifier_with(&mut Parser::new(&absent), false).is_ok());
+        
+        let not_null = [0x30, 0x0e,
+                        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+                        0x02, 0x01, 0x00];
+        assert_eq!(read_algorithm_identifier_with(&mut Parser::new(&not_null), true).err(),
+                   Some(Error::UnexpectedType));
+        assert!(read_algorithm_identifier(&mut Parser::new(&not_null)).is_ok());
+    }
+
+    #[test]
+    fn rsa_public_key() {
+        // A 1024-bit key, as found inside a SubjectPublicKeyInfo's BIT STRING.
+        let key = [0x30, 0x81, 0x89, 0x02, 0x81, 0x81, 0x00, 0xd4, 0xc4, 0x5d, 0x27, 0xc0, 0x46, 0x16, 0xbd, 0x8b,
+                   0x17, 0x1c, 0x45, 0x14, 0xd3, 0x48, 0x41, 0xdb, 0xb5, 0x1e, 0x0b, 0x3c, 0x07, 0x62, 0x2f, 0x0b,
+                   0x67, 0x3c, 0x2b, 0xd8, 0x61, 0xb8, 0xbf, 0xd0, 0x6c, 0xa7, 0xfc, 0x39, 0x11, 0x91, 0x8f, 0x01,
+                   0xf8, 0xc6, 0xb2, 0xd6, 0x7c, 0x57, 0xb5, 0xb8, 0xe6, 0x8c, 0x73, 0x60, 0xce, 0xba, 0xf6, 0xcc,
+                   0x69, 0x82, 0x83, 0x5d, 0xfc, 0x42, 0x67, 0x8a, 0xae, 0x62, 0x25, 0xfd, 0xae, 0x8f, 0x44, 0xd5,
+                   0x99, 0xc5, 0xc9, 0x15, 0x97, 0x1f, 0x32, 0x6c, 0xeb, 0x0b, 0xc0, 0x2c, 0x9b, 0x14, 0xc2, 0x92,
+                   0xee, 0xdf, 0x68, 0x9d, 0xfe, 0x8b, 0x19, 0xfb, 0x37, 0x18, 0xbc, 0xbf, 0xd6, 0xf9, 0xd2, 0x3c,
+                   0xd6, 0x95, 0xc7, 0xb4, 0xe5, 0xab, 0xb0, 0x40, 0xdb, 0x1d, 0x5f, 0x3d, 0x18, 0x92, 0x0f, 0x84,
+                   0x55, 0xc6, 0x93, 0x73, 0x20, 0x12, 0x85, 0x02, 0x03, 0x01, 0x00, 0x01];
+        let mut parser = Parser::new(&key);
+        let (modulus, exponent) = read_rsa_public_key(&mut parser).unwrap();
+        assert_eq!(modulus.bit_length(), 1024);
+        assert_eq!(modulus.as_bytes(), &key[6..135]);
+        assert_eq!(exponent.as_u32(), Some(65537));
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let negative = [0x30, 0x06, 0x02, 0x01, 0xc5, 0x02, 0x01, 0x03];
+        assert_eq!(read_rsa_public_key(&mut Parser::new(&negative)).err(), Some(Error::NegativeModulus));
+        
+        let extra = [0x30, 0x08, 0x02, 0x01, 0x45, 0x02, 0x01, 0x03, 0x05, 0x00];
+        assert_eq!(read_rsa_public_key(&mut Parser::new(&extra)).err(), Some(Error::UnexpectedType));
+    }
+
+    #[test]
+    fn private_key_info() {
+        // An Ed25519 key, whose privateKey is itself an OCTET STRING holding the seed.
+        let mut bs = vec![0x30, 0x2e,
+                          0x02, 0x01, 0x00,
+                          0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70,
+                          0x04, 0x22, 0x04, 0x20];
+        bs.extend((0..32).map(|x| x as u8));
+        let mut parser = Parser::new(&bs);
+        let (version, algorithm, private_key) = read_private_key_info(&mut parser).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(KnownOid::from_oid(&algorithm), KnownOid::Ed25519);
+        assert_eq!(private_key, &bs[14..]);
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let mut seed = Parser::new(private_key);
+        assert_eq!(seed.next_octet_string().unwrap().len(), 32);
+        
+        let missing_key = [0x30, 0x0a, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70];
+        assert_eq!(read_private_key_info(&mut Parser::new(&missing_key)).err(), Some(Error::EndOfStructure));
+    }
+
+    #[test]
+    fn name() {
+        let bs = [0x30, 0x1e,
+                  0x31, 0x0d, 0x30, 0x0b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x13, 0x04, b'J', b'a', b'n', b'e',
+                  0x31, 0x0d, 0x30, 0x0b, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x04, b'A', b'c', b'm', b'e'];
+        let attributes = read_name(&mut Parser::new(&bs)).unwrap();
+        assert_eq!(attributes.len(), 2);
+        
+        assert_eq!(attributes[0].0.as_bytes(), [0x55, 0x04, 0x03]);
+        match attributes[0].1 {
+            Asn1Value::PrintableString(x) => { assert_eq!(x, "Jane"); },
+            _ => { panic!("Expected a PrintableString"); }
+        }
+        
+        assert_eq!(attributes[1].0.as_bytes(), [0x55, 0x04, 0x0a]);
+        match attributes[1].1 {
+            Asn1Value::Utf8String(x) => { assert_eq!(x, "Acme"); },
+            _ => { panic!("Expected a UTF8String"); }
+        }
+        
+        let not_a_name = [0x30, 0x05, 0x30, 0x03, 0x02, 0x01, 0x01];
+        assert_eq!(read_name(&mut Parser::new(&not_a_name)).err(), Some(Error::UnexpectedType));
+    }
+}
@@ -20,16 +20,23 @@ fn is_printable_string(bs: &[u8]) -> bool {
     bs.iter().map(|x| *x).all(is_printable_char)
 }
 
-pub fn to_printable_string(bs: &[u8]) -> Result<&str, Error> {
+/// Checks that `bs` only contains PrintableString characters, returning it unchanged.
+pub fn validate(bs: &[u8]) -> Result<&[u8], Error> {
     if !is_printable_string(bs) {
         return Err(Error::InvalidPrintableString);
     }
+    Ok(bs)
+}
+
+pub fn to_printable_string(bs: &[u8]) -> Result<&str, Error> {
+    let bs = try!(validate(bs));
     str::from_utf8(bs).map_err(|_| Error::InvalidUTF8)
 }
 
 #[cfg(test)]
 mod test{
-    use super::to_printable_string;
+    use super::{to_printable_string, validate};
+    use error::Error;
 
     fn should_be_printable(x: u8) -> bool {
            (x >= b'A' && x<= b'Z')
@@ -62,6 +69,12 @@ mod test{
             }
         }
     }
-}
-
 
+    #[test]
+    fn validated_bytes() {
+        let bs = b"Example Org (2)";
+        assert_eq!(validate(bs), Ok(&bs[..]));
+        assert_eq!(validate(b"a@b"), Err(Error::InvalidPrintableString));
+        assert_eq!(validate(b""), Ok(&b""[..]));
+    }
+}
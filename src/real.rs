@@ -0,0 +1,225 @@
+use error::Error;
+use std::str;
+
+/// Decodes the content of a REAL. With `der` set, only DER's encodings are accepted: base 2
+/// with no scale factor and an odd mantissa, no negative zero, and decimal encodings only in
+/// the NR3 form.
+pub fn decode_real(content: &[u8], der: bool) -> Result<f64, Error> {
+    let (first, rest) = match content.split_first() {
+        Some((first, rest)) => (*first, rest),
+        None => { return Ok(0.0); }
+    };
+
+    if first & 0x80 != 0 {
+        return decode_binary(first, rest, der);
+    }
+    if first & 0x40 != 0 {
+        // The special values, which are always a single octet.
+        if !rest.is_empty() {
+            return Err(Error::InvalidReal);
+        }
+        return match first {
+            0x40 => Ok(f64::INFINITY),
+            0x41 => Ok(f64::NEG_INFINITY),
+            0x42 => Ok(f64::NAN),
+            0x43 if !der => Ok(-0.0),
+            _ => Err(Error::InvalidReal),
+        };
+    }
+
+    // A decimal encoding, whose low bits say which ISO 6093 form the text is in.
+    if first & 0x3f == 0 || first & 0x3f > 3 || (der && first & 0x3f != 3) {
+        return Err(Error::InvalidReal);
+    }
+    let text = try!(str::from_utf8(rest).map_err(|_| Error::InvalidReal));
+    let text = text.trim_start_matches(' ').replace(',', ".");
+    // Rust would also parse words like "inf", which aren't ISO 6093 numbers.
+    if !text.bytes().all(|x| x.is_ascii_digit() || b"+-.eE".contains(&x)) {
+        return Err(Error::InvalidReal);
+    }
+    let value = try!(text.parse::<f64>().map_err(|_| Error::InvalidReal));
+    if der && value == 0.0 && value.is_sign_negative() {
+        return Err(Error::InvalidReal);
+    }
+    Ok(value)
+}
+
+fn decode_binary(first: u8, rest: &[u8], der: bool) -> Result<f64, Error> {
+    let negative = first & 0x40 != 0;
+    let base_bits = match (first >> 4) & 0x03 {
+        0 => 1,
+        1 => 3,
+        2 => 4,
+        _ => { return Err(Error::InvalidReal); }
+    };
+    let scale = ((first >> 2) & 0x03) as i64;
+
+    let (exponent_length, rest) = match first & 0x03 {
+        3 => match rest.split_first() {
+            Some((length, rest)) if *length != 0 => (*length as usize, rest),
+            _ => { return Err(Error::InvalidReal); }
+        },
+        format => (format as usize + 1, rest),
+    };
+    if rest.len() <= exponent_length {
+        return Err(Error::InvalidReal); // there must be at least one mantissa byte
+    }
+    let (exponent_bytes, mantissa_bytes) = rest.split_at(exponent_length);
+
+    // Exponents too large for an i64 saturate, which still gives infinity or zero below.
+    let initial = if exponent_bytes[0] & 0x80 != 0 { -1i64 } else { 0i64 };
+    let exponent = exponent_bytes.iter().fold(initial, |accum, b| {
+        accum.checked_mul(256).map(|x| x | (*b as i64)).unwrap_or(if accum < 0 { i64::MIN } else { i64::MAX })
+    });
+
+    if der {
+        let redundant_exponent = exponent_bytes.len() > 1
+            && ((exponent_bytes[0] == 0x00 && exponent_bytes[1] & 0x80 == 0)
+             || (exponent_bytes[0] == 0xff && exponent_bytes[1] & 0x80 != 0));
+        let odd_mantissa = mantissa_bytes[mantissa_bytes.len() - 1] & 1 == 1;
+        if base_bits != 1 || scale != 0 || redundant_exponent || !odd_mantissa {
+            return Err(Error::InvalidReal);
+        }
+    }
+
+    let mantissa = mantissa_bytes.iter().fold(0f64, |accum, b| accum*256.0 + (*b as f64));
+    let power = exponent.saturating_mul(base_bits).saturating_add(scale);
+    let magnitude = if mantissa == 0.0 {
+        0.0
+    } else if power > 4096 {
+        f64::INFINITY
+    } else if power < -4096 {
+        0.0
+    } else {
+        // In steps, since 2^power alone may not fit in an f64 even when the result does.
+        let mut magnitude = mantissa;
+        let mut power = power as i32;
+        while power > 1000 {
+            magnitude *= 2f64.powi(1000);
+            power -= 1000;
+        }
+        while power < -1000 {
+            magnitude *= 2f64.powi(-1000);
+            power += 1000;
+        }
+        magnitude * 2f64.powi(power)
+    };
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Appends the DER content octets of `value`. Negative zero has no DER encoding, so it gives
+/// `UnencodableValue`.
+pub fn encode_real(value: f64, out: &mut Vec<u8>) -> Result<(), Error> {
+    if value.is_nan() {
+        out.push(0x42);
+        return Ok(());
+    }
+    if value.is_infinite() {
+        out.push(if value > 0.0 { 0x40 } else { 0x41 });
+        return Ok(());
+    }
+    if value == 0.0 {
+        if value.is_sign_negative() {
+            return Err(Error::UnencodableValue);
+        }
+        return Ok(());
+    }
+
+    // Split the f64 into an integer mantissa and a power of two, then make the mantissa odd.
+    let bits = value.to_bits();
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+    let fraction = bits & ((1 << 52) - 1);
+    let (mut mantissa, mut exponent) = if biased_exponent == 0 {
+        (fraction, -1074) // subnormal
+    } else {
+        (fraction | (1 << 52), biased_exponent - 1075)
+    };
+    let trailing = mantissa.trailing_zeros();
+    mantissa >>= trailing;
+    exponent += trailing as i64;
+
+    let exponent_bytes = (exponent as i16).to_be_bytes();
+    let exponent_bytes: &[u8] = if exponent >= -128 && exponent <= 127 { &exponent_bytes[1..] } else { &exponent_bytes };
+    let sign = if value < 0.0 { 0x40 } else { 0x00 };
+    out.push(0x80 | sign | (exponent_bytes.len() as u8 - 1));
+    out.extend_from_slice(exponent_bytes);
+
+    let mantissa_bytes = mantissa.to_be_bytes();
+    let leading_zeros = mantissa_bytes.iter().take_while(|x| **x == 0).count();
+    out.extend_from_slice(&mantissa_bytes[leading_zeros..]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_real, encode_real};
+    use error::Error;
+
+    #[test]
+    fn special_values() {
+        assert_eq!(decode_real(&[], true), Ok(0.0));
+        assert!(decode_real(&[], true).unwrap().is_sign_positive());
+        assert_eq!(decode_real(&[0x40], true), Ok(f64::INFINITY));
+        assert_eq!(decode_real(&[0x41], true), Ok(f64::NEG_INFINITY));
+        assert!(decode_real(&[0x42], true).unwrap().is_nan());
+        
+        // Negative zero is only allowed outside DER.
+        assert_eq!(decode_real(&[0x43], true), Err(Error::InvalidReal));
+        let minus_zero = decode_real(&[0x43], false).unwrap();
+        assert!(minus_zero == 0.0 && minus_zero.is_sign_negative());
+        
+        assert_eq!(decode_real(&[0x44], true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(&[0x40, 0x00], true), Err(Error::InvalidReal));
+    }
+
+    #[test]
+    fn binary() {
+        // 3 * 2^-1 and -5 * 2^4.
+        assert_eq!(decode_real(&[0x80, 0xff, 0x03], true), Ok(1.5));
+        assert_eq!(decode_real(&[0xc0, 0x04, 0x05], true), Ok(-80.0));
+        
+        // An even mantissa, base 16 and a padded exponent are all BER-only.
+        assert_eq!(decode_real(&[0x80, 0x00, 0x02], true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(&[0x80, 0x00, 0x02], false), Ok(2.0));
+        assert_eq!(decode_real(&[0xa0, 0x01, 0x01], false), Ok(16.0));
+        assert_eq!(decode_real(&[0xa0, 0x01, 0x01], true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(&[0x81, 0x00, 0x01, 0x01], true), Err(Error::InvalidReal));
+        
+        assert_eq!(decode_real(&[0x80, 0x01], true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(&[0x83, 0x00, 0x01], false), Err(Error::InvalidReal));
+    }
+
+    #[test]
+    fn decimal() {
+        assert_eq!(decode_real(b"\x03-1.5E2", true), Ok(-150.0));
+        assert_eq!(decode_real(b"\x01  42", false), Ok(42.0));
+        assert_eq!(decode_real(b"\x02 0,25", false), Ok(0.25));
+        
+        // DER only allows NR3.
+        assert_eq!(decode_real(b"\x01  42", true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(b"\x02 0,25", true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(b"\x03", true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(b"\x04 1", true), Err(Error::InvalidReal));
+        assert_eq!(decode_real(b"\x03inf", true), Err(Error::InvalidReal));
+        
+        // Negative zero written out in decimal is still negative zero.
+        assert_eq!(decode_real(b"\x03-0.E+0", true), Err(Error::InvalidReal));
+        assert!(decode_real(b"\x03-0.E+0", false).unwrap().is_sign_negative());
+    }
+
+    #[test]
+    fn round_trip() {
+        for &value in [1.0, -1.0, 1.5, -80.0, 0.1, 1e300, -1e-300, 5e-324, f64::MAX, f64::INFINITY,
+                       f64::NEG_INFINITY, 0.0].iter() {
+            let mut out = Vec::new();
+            encode_real(value, &mut out).unwrap();
+            assert_eq!(decode_real(&out, true), Ok(value), "{}", value);
+        }
+        
+        let mut out = Vec::new();
+        encode_real(1.5, &mut out).unwrap();
+        assert_eq!(out, [0x80, 0xff, 0x03]);
+        
+        assert_eq!(encode_real(-0.0, &mut out), Err(Error::UnencodableValue));
+    }
+}
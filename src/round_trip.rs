@@ -0,0 +1,146 @@
+//! Property tests that encode randomly generated trees of values with `Writer` and
+//! `Asn1Value::encode`, parse them back, and check that the same tree comes out.
+
+use proptest::prelude::*;
+
+use ::{Asn1Value, Parser, Writer};
+use encoder::der_set_cmp;
+use integer::Integer;
+use object_identifier::ObjectIdentifier;
+
+/// An owned value, so that trees can be generated (and shrunk) independently of any encoding.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Null,
+    Boolean(bool),
+    // Minimal two's-complement bytes.
+    Integer(Vec<u8>),
+    OctetString(Vec<u8>),
+    Utf8String(String),
+    PrintableString(String),
+    ObjectIdentifier(Vec<u32>),
+    Sequence(Vec<Node>),
+    Set(Vec<Node>),
+}
+
+impl Node {
+    fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        let mut leaf = Vec::new();
+        match *self {
+            Node::Null => Asn1Value::Null.encode(&mut leaf).unwrap(),
+            Node::Boolean(x) => writer.write_bool(x),
+            Node::Integer(ref x) => Asn1Value::Integer(Integer::new(x)).encode(&mut leaf).unwrap(),
+            Node::OctetString(ref x) => Asn1Value::OctetString(x).encode(&mut leaf).unwrap(),
+            Node::Utf8String(ref x) => Asn1Value::Utf8String(x).encode(&mut leaf).unwrap(),
+            Node::PrintableString(ref x) => Asn1Value::PrintableString(x).encode(&mut leaf).unwrap(),
+            Node::ObjectIdentifier(ref arcs) => writer.write_oid_arcs(arcs).unwrap(),
+            Node::Sequence(ref children) => {
+                writer.begin_sequence();
+                for child in children {
+                    writer.write_raw(&child.encode());
+                }
+                writer.end_sequence().unwrap();
+            }
+            Node::Set(ref children) => {
+                let members: Vec<Vec<u8>> = children.iter().map(Node::encode).collect();
+                writer.write_sorted_set(&members);
+            }
+        }
+        writer.write_raw(&leaf);
+        writer.into_bytes()
+    }
+
+    /// The tree as it will be parsed back, which has SET members in DER order.
+    fn canonical(&self) -> Node {
+        match *self {
+            Node::Sequence(ref children) => Node::Sequence(children.iter().map(Node::canonical).collect()),
+            Node::Set(ref children) => {
+                let mut children: Vec<Node> = children.iter().map(Node::canonical).collect();
+                children.sort_by(|a, b| der_set_cmp(&a.encode(), &b.encode()));
+                Node::Set(children)
+            }
+            ref leaf => leaf.clone(),
+        }
+    }
+}
+
+fn read_node(parser: &mut Parser) -> Node {
+    match parser.next().unwrap() {
+        Asn1Value::Null => Node::Null,
+        Asn1Value::Boolean(x) => Node::Boolean(x),
+        Asn1Value::Integer(x) => Node::Integer(x.as_bytes().to_vec()),
+        Asn1Value::OctetString(x) => Node::OctetString(x.to_vec()),
+        Asn1Value::Utf8String(x) => Node::Utf8String(x.to_string()),
+        Asn1Value::PrintableString(x) => Node::PrintableString(x.to_string()),
+        Asn1Value::ObjectIdentifier(x) => Node::ObjectIdentifier(oid_arcs(&x)),
+        Asn1Value::SequenceStart => Node::Sequence(read_children(parser)),
+        Asn1Value::SetStart => Node::Set(read_children(parser)),
+        _ => { panic!("Expected a generated value"); }
+    }
+}
+
+fn read_children(parser: &mut Parser) -> Vec<Node> {
+    let mut children = Vec::new();
+    while !parser.remaining().is_empty() {
+        children.push(read_node(parser));
+    }
+    match parser.next().unwrap() {
+        Asn1Value::SequenceEnd | Asn1Value::SetEnd => {},
+        _ => { panic!("Expected the end of a structure"); }
+    }
+    children
+}
+
+fn oid_arcs(oid: &ObjectIdentifier) -> Vec<u32> {
+    oid.iter().collect()
+}
+
+/// Strips redundant sign bytes, so that the generated INTEGER is valid DER.
+fn minimal_integer(mut bytes: Vec<u8>) -> Vec<u8> {
+    if bytes.is_empty() {
+        bytes.push(0);
+    }
+    while bytes.len() > 1 && ((bytes[0] == 0x00 && bytes[1] & 0x80 == 0)
+                           || (bytes[0] == 0xff && bytes[1] & 0x80 != 0)) {
+        bytes.remove(0);
+    }
+    bytes
+}
+
+fn leaf() -> BoxedStrategy<Node> {
+    prop_oneof![
+        Just(Node::Null),
+        any::<bool>().prop_map(Node::Boolean),
+        prop::collection::vec(any::<u8>(), 0..40).prop_map(|x| Node::Integer(minimal_integer(x))),
+        // Long enough to cross the 127/128 byte boundary between short and long form lengths.
+        prop::collection::vec(any::<u8>(), 0..300).prop_map(Node::OctetString),
+        ".{0,140}".prop_map(Node::Utf8String),
+        "[A-Za-z0-9 '()+,./:=?-]{0,20}".prop_map(Node::PrintableString),
+        (0..3u32, 0..40u32, prop::collection::vec(any::<u32>(), 0..6)).prop_map(|(first, second, rest)| {
+            let mut arcs = vec![first, second];
+            arcs.extend(rest);
+            Node::ObjectIdentifier(arcs)
+        }),
+    ].boxed()
+}
+
+fn tree() -> BoxedStrategy<Node> {
+    leaf().prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Node::Sequence),
+            prop::collection::vec(inner, 0..8).prop_map(Node::Set),
+        ]
+    }).boxed()
+}
+
+proptest! {
+    #[test]
+    fn encoded_trees_parse_back(node in tree()) {
+        let bytes = node.encode();
+        let mut parser = Parser::new(&bytes);
+        let parsed = read_node(&mut parser);
+        prop_assert!(parser.remaining().is_empty());
+        prop_assert_eq!(parsed, node.canonical());
+    }
+}
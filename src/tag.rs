@@ -0,0 +1,128 @@
+use error::Error;
+
+/// Ordered as DER orders the members of a SET: universal tags first, private ones last.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TagClass {
+    Universal,
+    Application,
+    ContextSpecific,
+    Private,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub class: TagClass,
+    pub constructed: bool,
+    pub number: u32,
+}
+
+impl Tag {
+    /// Decodes the identifier octets at the start of `bytes`, returning the tag along with
+    /// the number of bytes it occupied.
+    pub fn decode(bytes: &[u8]) -> Result<(Tag, usize), Error> {
+        let initial = match bytes.first() {
+            Some(initial) => *initial,
+            None => { return Err(Error::EOF); }
+        };
+        
+        let class = match initial >> 6 {
+            0 => TagClass::Universal,
+            1 => TagClass::Application,
+            2 => TagClass::ContextSpecific,
+            _ => TagClass::Private,
+        };
+        let constructed = initial & 0x20 != 0;
+        
+        if initial & 0x1f != 0x1f {
+            return Ok((Tag{ class: class, constructed: constructed, number: (initial & 0x1f) as u32 }, 1));
+        }
+        
+        // High tag number form: the number follows in base 128, high bit set on all but the
+        // last byte. As with OID components, we allow up to 4 bytes so it fits in a u32.
+        let mut number = 0u32;
+        for (idx, byte) in bytes[1..].iter().enumerate() {
+            if idx == 0 && *byte == 0x80 {
+                return Err(Error::Malformed); // This byte was not needed!
+            }
+            if idx >= 4 {
+                return Err(Error::TagTooLarge);
+            }
+            
+            number = (number<<7) | ((*byte & 0x7f) as u32);
+            if *byte & 0x80 == 0 {
+                if number < 0x1f {
+                    return Err(Error::Malformed); // Should have used the one-byte form
+                }
+                return Ok((Tag{ class: class, constructed: constructed, number: number }, idx + 2));
+            }
+        }
+        
+        Err(Error::EOF)
+    }
+
+    /// The first identifier octet for this tag, which is the whole tag if its number is
+    /// below 31.
+    pub fn first_octet(&self) -> u8 {
+        let class_bits = match self.class {
+            TagClass::Universal => 0x00,
+            TagClass::Application => 0x40,
+            TagClass::ContextSpecific => 0x80,
+            TagClass::Private => 0xc0,
+        };
+        let constructed_bit = if self.constructed { 0x20 } else { 0x00 };
+        class_bits | constructed_bit | (self.number.min(0x1f) as u8)
+    }
+
+    /// Appends the identifier octets for this tag.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.first_octet());
+        if self.number < 0x1f {
+            return;
+        }
+        
+        let mut shift = 28;
+        while shift > 0 && (self.number >> shift) == 0 {
+            shift -= 7;
+        }
+        while shift > 0 {
+            out.push(0x80 | ((self.number >> shift) & 0x7f) as u8);
+            shift -= 7;
+        }
+        out.push((self.number & 0x7f) as u8);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Tag, TagClass};
+
+    #[test]
+    fn decode() {
+        assert_eq!(Tag::decode(&[0x30]).unwrap(),
+                   (Tag{ class: TagClass::Universal, constructed: true, number: 16 }, 1));
+        assert_eq!(Tag::decode(&[0xa3, 0x00]).unwrap(),
+                   (Tag{ class: TagClass::ContextSpecific, constructed: true, number: 3 }, 1));
+        assert_eq!(Tag::decode(&[0x9f, 0x1f]).unwrap(),
+                   (Tag{ class: TagClass::ContextSpecific, constructed: false, number: 31 }, 2));
+        assert_eq!(Tag::decode(&[0x5f, 0x81, 0x00]).unwrap(),
+                   (Tag{ class: TagClass::Application, constructed: false, number: 128 }, 3));
+        
+        assert!(Tag::decode(&[]).is_err());
+        assert!(Tag::decode(&[0x9f]).is_err()); // Missing the number
+        assert!(Tag::decode(&[0x9f, 0x81]).is_err()); // Ends in the middle of the number
+        assert!(Tag::decode(&[0x9f, 0x80, 0x01]).is_err()); // The 0x80 is unnecessary
+        assert!(Tag::decode(&[0x9f, 0x1e]).is_err()); // Fits in the one-byte form
+        assert!(Tag::decode(&[0x9f, 0x81, 0x81, 0x81, 0x81, 0x01]).is_err());
+    }
+
+    #[test]
+    fn encode() {
+        for bytes in [&[0x30][..], &[0xa3], &[0x9f, 0x1f], &[0x5f, 0x81, 0x00], &[0xff, 0x8f, 0xff, 0xff, 0x7f]].iter() {
+            let (tag, _) = Tag::decode(bytes).unwrap();
+            let mut out = Vec::new();
+            tag.encode(&mut out);
+            assert_eq!(&out[..], *bytes);
+            assert_eq!(tag.first_octet(), bytes[0]);
+        }
+    }
+}
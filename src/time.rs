@@ -0,0 +1,226 @@
+use error::Error;
+#[cfg(feature = "chrono")]
+use chrono::{self, TimeZone};
+
+/// A UTC date and time, as decoded from either a UTCTime or a GeneralizedTime. Fields are
+/// ordered from most to least significant, so the derived ordering is chronological.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Parses a fixed-width run of decimal digits.
+fn digits(bs: &[u8]) -> Result<u32, Error> {
+    if bs.is_empty() || !bs.iter().all(|x| x.is_ascii_digit()) {
+        return Err(Error::InvalidTime);
+    }
+    Ok(bs.iter().fold(0, |accum, x| accum*10 + (*x - b'0') as u32))
+}
+
+impl DateTime {
+    /// Decodes the content of a UTCTime in its DER form, `YYMMDDHHMMSSZ`. As in RFC 5280,
+    /// two-digit years from 50 on are in the 1900s, and the rest in the 2000s.
+    pub fn from_utc_time(bs: &[u8]) -> Result<DateTime, Error> {
+        if bs.len() != 13 || bs[12] != b'Z' {
+            return Err(Error::InvalidTime);
+        }
+        
+        let two_digit_year = try!(digits(&bs[0..2])) as u16;
+        let year = if two_digit_year >= 50 { 1900 + two_digit_year } else { 2000 + two_digit_year };
+        DateTime::from_fields(year, &bs[2..12], 0)
+    }
+
+    /// Decodes the content of a GeneralizedTime in its DER form, `YYYYMMDDHHMMSS[.f]Z`,
+    /// where the optional fraction of a second has no trailing zeros.
+    pub fn from_generalized_time(bs: &[u8]) -> Result<DateTime, Error> {
+        if bs.len() < 15 || bs[bs.len() - 1] != b'Z' {
+            return Err(Error::InvalidTime);
+        }
+        
+        let year = try!(digits(&bs[0..4])) as u16;
+        
+        let fraction = &bs[14 .. bs.len() - 1];
+        let nanosecond = if fraction.is_empty() {
+            0
+        } else {
+            let fraction_digits = &fraction[1..];
+            if fraction[0] != b'.' || fraction_digits.len() > 9 || fraction_digits.last() == Some(&b'0') {
+                return Err(Error::InvalidTime);
+            }
+            try!(digits(fraction_digits)) * 10u32.pow(9 - fraction_digits.len() as u32)
+        };
+        
+        DateTime::from_fields(year, &bs[4..14], nanosecond)
+    }
+
+    /// Builds a DateTime from the year and the `MMDDHHMMSS` digits that follow it.
+    fn from_fields(year: u16, bs: &[u8], nanosecond: u32) -> Result<DateTime, Error> {
+        let date_time = DateTime{
+            year: year,
+            month: try!(digits(&bs[0..2])) as u8,
+            day: try!(digits(&bs[2..4])) as u8,
+            hour: try!(digits(&bs[4..6])) as u8,
+            minute: try!(digits(&bs[6..8])) as u8,
+            second: try!(digits(&bs[8..10])) as u8,
+            nanosecond: nanosecond,
+        };
+        try!(date_time.validate());
+        Ok(date_time)
+    }
+
+    /// Checks that every field is in range, for instance that the day exists in the month.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.month < 1 || self.month > 12
+            || self.day < 1 || self.day > days_in_month(self.year, self.month)
+            || self.hour > 23 || self.minute > 59 || self.second > 59
+            || self.nanosecond > 999_999_999 {
+            return Err(Error::InvalidTime);
+        }
+        Ok(())
+    }
+
+    /// Converts this to a chrono timestamp. Returns `InvalidTime` if a field is out of range.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        try!(self.validate());
+        chrono::NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32)
+            .and_then(|date| date.and_hms_nano_opt(self.hour as u32, self.minute as u32, self.second as u32, self.nanosecond))
+            .map(|naive| chrono::Utc.from_utc_datetime(&naive))
+            .ok_or(Error::InvalidTime)
+    }
+
+    /// Formats this as the content of a DER UTCTime, which can only represent the years
+    /// 1950 through 2049, and whole seconds.
+    pub fn to_utc_time(&self) -> Result<String, Error> {
+        try!(self.validate());
+        if self.year < 1950 || self.year > 2049 || self.nanosecond != 0 {
+            return Err(Error::InvalidTime);
+        }
+        
+        Ok(format!("{:02}{:02}{:02}{:02}{:02}{:02}Z",
+                   self.year % 100, self.month, self.day, self.hour, self.minute, self.second))
+    }
+
+    /// Formats this as the content of a DER GeneralizedTime.
+    pub fn to_generalized_time(&self) -> Result<String, Error> {
+        try!(self.validate());
+        if self.year > 9999 {
+            return Err(Error::InvalidTime);
+        }
+        
+        let mut formatted = format!("{:04}{:02}{:02}{:02}{:02}{:02}",
+                                    self.year, self.month, self.day, self.hour, self.minute, self.second);
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            formatted.push('.');
+            formatted.push_str(fraction.trim_end_matches('0'));
+        }
+        formatted.push('Z');
+        Ok(formatted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DateTime;
+    use ::{Asn1Value, Parser};
+    use error::Error;
+
+    fn date_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime{ year: year, month: month, day: day, hour: hour, minute: minute, second: second, nanosecond: 0 }
+    }
+
+    #[test]
+    fn utc_time() {
+        assert_eq!(DateTime::from_utc_time(b"491231235959Z"), Ok(date_time(2049, 12, 31, 23, 59, 59)));
+        assert_eq!(DateTime::from_utc_time(b"500101000000Z"), Ok(date_time(1950, 1, 1, 0, 0, 0)));
+        assert_eq!(DateTime::from_utc_time(b"000229120000Z"), Ok(date_time(2000, 2, 29, 12, 0, 0)));
+        
+        for bad in [&b"010229120000Z"[..], b"011301000000Z", b"010100000000Z", b"010101240000Z",
+                    b"0101010000Z", b"010101000000", b"0101010000000Z", b"01010100000+Z",
+                    b"010101000000+0000"].iter() {
+            assert_eq!(DateTime::from_utc_time(bad), Err(Error::InvalidTime), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn generalized_time() {
+        assert_eq!(DateTime::from_generalized_time(b"20491231235959Z"), Ok(date_time(2049, 12, 31, 23, 59, 59)));
+        assert_eq!(DateTime::from_generalized_time(b"19000228000000Z"), Ok(date_time(1900, 2, 28, 0, 0, 0)));
+        
+        let fractional = DateTime::from_generalized_time(b"20200101000000.25Z").unwrap();
+        assert_eq!(fractional.nanosecond, 250_000_000);
+        assert!(fractional > date_time(2020, 1, 1, 0, 0, 0));
+        
+        for bad in [&b"19000229000000Z"[..], b"20200101000000.Z", b"20200101000000.50Z",
+                    b"20200101000000,5Z", b"202001010000Z", b"20200101000000"].iter() {
+            assert_eq!(DateTime::from_generalized_time(bad), Err(Error::InvalidTime), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        let bs = [0x17, 0x0d, b'2', b'4', b'0', b'6', b'3', b'0', b'1', b'2', b'0', b'0', b'0', b'0', b'Z',
+                  0x18, 0x0f, b'2', b'0', b'2', b'4', b'0', b'6', b'3', b'0', b'1', b'2', b'0', b'0', b'0', b'0', b'Z',
+                  0x18, 0x0f, b'2', b'0', b'5', b'0', b'0', b'1', b'0', b'1', b'0', b'0', b'0', b'0', b'0', b'0', b'Z'];
+        let mut parser = Parser::new(&bs);
+        let utc = match parser.next().unwrap() {
+            Asn1Value::UtcTime(x) => x,
+            _ => { panic!("Expected a UTCTime"); }
+        };
+        let generalized = match parser.next().unwrap() {
+            Asn1Value::GeneralizedTime(x) => x,
+            _ => { panic!("Expected a GeneralizedTime"); }
+        };
+        let later = match parser.next().unwrap() {
+            Asn1Value::GeneralizedTime(x) => x,
+            _ => { panic!("Expected a GeneralizedTime"); }
+        };
+        
+        assert_eq!(utc, generalized);
+        assert!(utc <= generalized && generalized <= utc);
+        assert!(utc < later);
+        
+        for sample in [&bs[0..15], &bs[15..32], &bs[32..]].iter() {
+            let mut out = Vec::new();
+            Parser::new(sample).next().unwrap().encode(&mut out).unwrap();
+            assert_eq!(&out[..], *sample);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        use chrono::{TimeZone, Utc};
+        
+        let utc = DateTime::from_utc_time(b"991231235959Z").unwrap();
+        assert_eq!(utc.to_chrono(), Ok(Utc.with_ymd_and_hms(1999, 12, 31, 23, 59, 59).unwrap()));
+        let utc = DateTime::from_utc_time(b"240630120000Z").unwrap();
+        assert_eq!(utc.to_chrono(), Ok(Utc.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap()));
+        
+        let generalized = DateTime::from_generalized_time(b"21060207062815.5Z").unwrap();
+        assert_eq!(generalized.to_chrono().unwrap().timestamp_millis(), 4294967295500);
+        
+        assert_eq!(date_time(2023, 2, 29, 0, 0, 0).to_chrono(), Err(Error::InvalidTime));
+    }
+}
@@ -0,0 +1,161 @@
+use ::{Asn1Value, Parser};
+use error::Error;
+use tag::{Tag, TagClass};
+use {decode_length, MAX_NESTING_DEPTH};
+
+/// A value's tag, length and content, without any interpretation of what the tag means.
+/// Constructed values have their content parsed into children as well.
+#[derive(Debug, Clone)]
+pub struct RawTlv<'a> {
+    pub tag: u32,
+    pub class: TagClass,
+    pub constructed: bool,
+    pub content: &'a [u8],
+    pub children: Vec<RawTlv<'a>>,
+}
+
+/// Parses `input` as a series of values, descending into every constructed one. Structures
+/// nested more than `MAX_NESTING_DEPTH` deep give `NestingTooDeep`.
+pub fn parse_tlv_tree(input: &[u8]) -> Result<Vec<RawTlv<'_>>, Error> {
+    parse_tlv_level(input, 0)
+}
+
+fn parse_tlv_level(input: &[u8], depth: usize) -> Result<Vec<RawTlv<'_>>, Error> {
+    let mut nodes = Vec::new();
+    let mut rest = input;
+    
+    while !rest.is_empty() {
+        let (tag, tag_size) = try!(Tag::decode(rest));
+        let (length, length_size) = try!(decode_length(&rest[tag_size..]));
+        let header_size = tag_size + length_size;
+        if length > rest.len() - header_size {
+            return Err(Error::EOF);
+        }
+        
+        let content = &rest[header_size .. header_size + length];
+        let children = if tag.constructed {
+            if depth == MAX_NESTING_DEPTH {
+                return Err(Error::NestingTooDeep);
+            }
+            try!(parse_tlv_level(content, depth + 1))
+        } else {
+            Vec::new()
+        };
+        
+        nodes.push(RawTlv{
+            tag: tag.number,
+            class: tag.class,
+            constructed: tag.constructed,
+            content: content,
+            children: children,
+        });
+        rest = &rest[header_size + length ..];
+    }
+    
+    Ok(nodes)
+}
+
+/// Counts the values in `input`, both primitive ones and structures (but not their end
+/// markers), so that storage for them can be allocated up front.
+pub fn count_nodes(input: &[u8]) -> Result<usize, Error> {
+    let mut parser = Parser::new(input);
+    let mut count = 0;
+    
+    while parser.position < input.len() || !parser.structures.is_empty() {
+        match try!(parser.next()) {
+            Asn1Value::SequenceEnd |
+            Asn1Value::SetEnd |
+            Asn1Value::TaggedEnd |
+            Asn1Value::UnknownEnd => {},
+            _ => { count += 1; }
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{count_nodes, parse_tlv_tree};
+    use tag::TagClass;
+    use error::Error;
+    use encoder::Writer;
+    use MAX_NESTING_DEPTH;
+
+    fn nested_sequences(depth: usize) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for _ in 0..depth {
+            writer.begin_sequence();
+        }
+        for _ in 0..depth {
+            writer.end_sequence().unwrap();
+        }
+        writer.into_bytes()
+    }
+
+    #[test]
+    fn tree() {
+        let bs = [0x30, 0x0a,
+                  0xa0, 0x03,
+                  0x02, 0x01, 0x02,
+                  0x31, 0x00,
+                  0x9f, 0x20, 0x00,
+                  0x05, 0x00];
+        let nodes = parse_tlv_tree(&bs).unwrap();
+        assert_eq!(nodes.len(), 2);
+        
+        let sequence = &nodes[0];
+        assert_eq!((sequence.class, sequence.tag, sequence.constructed), (TagClass::Universal, 16, true));
+        assert_eq!(sequence.content, &bs[2..12]);
+        assert_eq!(sequence.children.len(), 3);
+        
+        let explicit = &sequence.children[0];
+        assert_eq!((explicit.class, explicit.tag, explicit.constructed), (TagClass::ContextSpecific, 0, true));
+        assert_eq!(explicit.children.len(), 1);
+        assert_eq!(explicit.children[0].content, [0x02]);
+        assert!(explicit.children[0].children.is_empty());
+        
+        assert!(sequence.children[1].constructed && sequence.children[1].children.is_empty());
+        assert_eq!((sequence.children[2].class, sequence.children[2].tag), (TagClass::ContextSpecific, 32));
+        
+        assert_eq!((nodes[1].class, nodes[1].tag), (TagClass::Universal, 5));
+    }
+
+    #[test]
+    fn node_count() {
+        let bs = [0x30, 0x0a,
+                  0xa0, 0x03,
+                  0x02, 0x01, 0x02,
+                  0x31, 0x00,
+                  0x9f, 0x20, 0x00,
+                  0x05, 0x00];
+        assert_eq!(count_nodes(&bs), Ok(6));
+        assert_eq!(count_nodes(&[]), Ok(0));
+        assert_eq!(count_nodes(&bs[..11]).err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(parse_tlv_tree(&[0x30, 0x03, 0x02, 0x01]).err(), Some(Error::EOF));
+        assert_eq!(parse_tlv_tree(&[0x30, 0x02, 0x02, 0x01]).err(), Some(Error::EOF));
+        assert!(parse_tlv_tree(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let bs = nested_sequences(MAX_NESTING_DEPTH);
+        let mut nodes = parse_tlv_tree(&bs).unwrap();
+        let mut depth = 0;
+        while let Some(node) = nodes.pop() {
+            nodes = node.children;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_NESTING_DEPTH);
+        
+        assert_eq!(parse_tlv_tree(&nested_sequences(MAX_NESTING_DEPTH + 1)).err(), Some(Error::NestingTooDeep));
+        let bs = nested_sequences(100_000);
+        assert_eq!(parse_tlv_tree(&bs).err(), Some(Error::NestingTooDeep));
+        
+        // count_nodes streams through a Parser, so any depth is fine.
+        assert_eq!(count_nodes(&bs), Ok(100_000));
+    }
+}
@@ -0,0 +1,155 @@
+use ::{Asn1Value, Parser};
+use bit_string::BitString;
+use error::Error;
+use object_identifier::ObjectIdentifier;
+use pki::read_algorithm_identifier;
+use tag::{Tag, TagClass};
+
+/// A certificate extension, with its extnValue left for the caller to decode according
+/// to `oid`.
+pub struct Extension<'a> {
+    pub oid: ObjectIdentifier<'a>,
+    pub critical: bool,
+    /// A parser over the DER inside the extnValue OCTET STRING.
+    pub value_parser: Parser<'a>,
+}
+
+/// Opens the outermost `Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm,
+/// signatureValue }`, checking that it spans all of `der`. The returned parser is positioned
+/// at the tbsCertificate.
+pub fn open_certificate(der: &[u8]) -> Result<Parser<'_>, Error> {
+    let mut parser = Parser::new(der);
+    
+    let sequence = Tag{ class: TagClass::Universal, constructed: true, number: 0x10 };
+    if try!(parser.peek_tag()) != sequence {
+        return Err(Error::UnexpectedType);
+    }
+    let (header_length, content_length) = try!(parser.peek_length());
+    if content_length > der.len() - header_length {
+        return Err(Error::EOF);
+    }
+    if header_length + content_length != der.len() {
+        return Err(Error::TrailingData);
+    }
+    
+    match try!(parser.next()) {
+        Asn1Value::SequenceStart => Ok(parser),
+        _ => Err(Error::UnexpectedType),
+    }
+}
+
+/// Reads a `SubjectPublicKeyInfo ::= SEQUENCE { algorithm AlgorithmIdentifier,
+/// subjectPublicKey BIT STRING }`, returning the algorithm's OID and the undecoded key.
+pub fn read_spki<'a>(parser: &mut Parser<'a>) -> Result<(ObjectIdentifier<'a>, BitString<'a>), Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let (algorithm, _) = try!(read_algorithm_identifier(parser));
+    let public_key = try!(parser.next_bit_string());
+    try!(parser.expect_end());
+    Ok((algorithm, public_key))
+}
+
+/// Reads an `Extension ::= SEQUENCE { extnID OBJECT IDENTIFIER, critical BOOLEAN DEFAULT
+/// FALSE, extnValue OCTET STRING }`.
+pub fn read_extension<'a>(parser: &mut Parser<'a>) -> Result<Extension<'a>, Error> {
+    match try!(parser.next_element()) {
+        Asn1Value::SequenceStart => {},
+        _ => { return Err(Error::UnexpectedType); }
+    }
+    
+    let oid = try!(parser.next_object_identifier());
+    let critical = try!(parser.read_optional_bool(false));
+    let value_parser = try!(parser.read_octet_string_as_parser());
+    try!(parser.expect_end());
+    Ok(Extension{ oid: oid, critical: critical, value_parser: value_parser })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{open_certificate, read_extension, read_spki};
+    use ::{Asn1Value, Parser};
+    use algorithm::KnownOid;
+    use error::Error;
+    use pki::read_algorithm_identifier;
+
+    #[test]
+    fn certificate() {
+        // The fields are stand-ins, but the shape is that of a certificate.
+        let bs = [0x30, 0x14,
+                  0x30, 0x03, 0x02, 0x01, 0x01,
+                  0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70,
+                  0x03, 0x06, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00];
+        let mut parser = open_certificate(&bs).unwrap();
+        match parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected the tbsCertificate"); }
+        }
+        assert_eq!(parser.next_integer().unwrap().as_u8(), Some(1));
+        assert!(parser.next().is_ok());
+        assert!(read_algorithm_identifier(&mut parser).is_ok());
+        assert_eq!(parser.next_bit_string().unwrap().len(), 40);
+        match parser.next().unwrap() {
+            Asn1Value::SequenceEnd => {},
+            _ => { panic!("Expected the end of the certificate"); }
+        }
+        
+        let mut trailing = bs.to_vec();
+        trailing.push(0x00);
+        assert_eq!(open_certificate(&trailing).err(), Some(Error::TrailingData));
+        assert_eq!(open_certificate(&bs[..10]).err(), Some(Error::EOF));
+        assert_eq!(open_certificate(&[0x31, 0x00]).err(), Some(Error::UnexpectedType));
+        assert_eq!(open_certificate(&[]).err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn extension() {
+        // A critical BasicConstraints extension with cA set, followed by a non-critical one
+        // whose value is an empty SEQUENCE.
+        let bs = [0x30, 0x0f,
+                  0x06, 0x03, 0x55, 0x1d, 0x13,
+                  0x01, 0x01, 0xff,
+                  0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xff,
+                  0x30, 0x09,
+                  0x06, 0x03, 0x55, 0x1d, 0x13,
+                  0x04, 0x02, 0x30, 0x00];
+        let mut parser = Parser::new(&bs);
+        
+        let mut extension = read_extension(&mut parser).unwrap();
+        assert!(extension.oid.equals_dotted("2.5.29.19"));
+        assert!(extension.critical);
+        match extension.value_parser.next().unwrap() {
+            Asn1Value::SequenceStart => {},
+            _ => { panic!("Expected BasicConstraints"); }
+        }
+        assert_eq!(extension.value_parser.next_boolean(), Ok(true));
+        
+        let extension = read_extension(&mut parser).unwrap();
+        assert!(!extension.critical);
+        assert_eq!(extension.value_parser.remaining(), [0x30, 0x00]);
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+    }
+
+    #[test]
+    fn spki() {
+        // An RSA key with a (tiny) modulus of 0x00c5 and exponent 65537.
+        let bs = [0x30, 0x1d,
+                  0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+                  0x03, 0x0c, 0x00,
+                  0x30, 0x09, 0x02, 0x02, 0x00, 0xc5, 0x02, 0x03, 0x01, 0x00, 0x01];
+        let mut parser = Parser::new(&bs);
+        let (algorithm, public_key) = read_spki(&mut parser).unwrap();
+        assert_eq!(KnownOid::from_oid(&algorithm), KnownOid::RsaEncryption);
+        assert_eq!(public_key.data(), &bs[20..]);
+        assert_eq!(parser.next().err(), Some(Error::EOF));
+        
+        let mut key = Parser::new(public_key.data());
+        assert!(key.next().is_ok());
+        assert_eq!(key.next_integer().unwrap().as_u8(), Some(0xc5));
+        assert_eq!(key.next_integer().unwrap().as_u32(), Some(65537));
+        assert!(key.next().is_ok());
+        assert_eq!(key.next().err(), Some(Error::EOF));
+    }
+}